@@ -0,0 +1,262 @@
+//! Markdown checklists in notes, kept in sync with child tasks. Each unchecked item (`- [ ] item`)
+//! becomes a task which the note's task depends on, and the child's UUID is kept at the end of
+//! the item in a comment, so that it doesn't show up when the note is rendered:
+//!
+//! ```text
+//! - [ ] book the venue <!-- taskn:6fd0b8f3-4aa0-4a38-a43b-dfcb1b5ed6a5 -->
+//! ```
+//!
+//! Checking an item completes its child task, and completing the child task checks the item.
+//! Items which are already checked when they're written don't get a task.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::Local;
+
+use crate::error::Result;
+use crate::notes::NoteStore;
+use crate::taskwarrior::{ParsableDateTime, Task, TaskBackend};
+
+const MARKER_START: &str = "<!-- taskn:";
+const MARKER_END: &str = "-->";
+
+/// A single checklist item, i.e. a line like `- [x] item`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    /// Everything on the line before the box, e.g. the indentation and the `- `.
+    prefix: String,
+    pub checked: bool,
+    pub text: String,
+    /// The UUID of the item's task, once it has one.
+    pub uuid: Option<String>,
+}
+
+impl Item {
+    /// Parses `line` as a checklist item, if it is one.
+    pub fn parse(line: &str) -> Option<Self> {
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        let bullet = trimmed.chars().next().filter(|c| "-*+".contains(*c))?;
+        let rest = trimmed[1..].strip_prefix(" [")?;
+        let checked = match rest.chars().next()? {
+            ' ' => false,
+            'x' | 'X' => true,
+            _ => return None,
+        };
+        let text = rest[1..].strip_prefix("] ")?.trim_end();
+
+        let (text, uuid) = match text
+            .strip_suffix(MARKER_END)
+            .and_then(|text| text.rfind(MARKER_START).map(|start| (text, start)))
+        {
+            Some((text, start)) => (
+                text[..start].trim_end(),
+                Some(text[start + MARKER_START.len()..].trim().to_string()),
+            ),
+            None => (text, None),
+        };
+        Some(Item {
+            prefix: format!("{}{} ", indent, bullet),
+            checked,
+            text: text.to_string(),
+            uuid,
+        })
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}",
+            self.prefix,
+            if self.checked { 'x' } else { ' ' },
+            self.text
+        )?;
+        if let Some(uuid) = &self.uuid {
+            write!(f, " {}{} {}", MARKER_START, uuid, MARKER_END)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the checked items and all of the items in `contents`, e.g. `(3, 5)`. Returns `None` when
+/// there's no checklist.
+pub fn progress(contents: &str) -> Option<(usize, usize)> {
+    let items = contents
+        .lines()
+        .filter_map(Item::parse)
+        .collect::<Vec<Item>>();
+    if items.is_empty() {
+        return None;
+    }
+    Some((
+        items.iter().filter(|item| item.checked).count(),
+        items.len(),
+    ))
+}
+
+/// Checks the items in `contents` whose tasks have been completed, without changing any tasks.
+pub fn refresh(backend: &dyn TaskBackend, contents: &str) -> Result<String> {
+    let mut children = children(backend, contents)?;
+    rewrite(contents, |item| {
+        if let Some(child) = item.uuid.as_ref().and_then(|uuid| children.remove(uuid)) {
+            item.checked |= child.status == "completed";
+        }
+        Ok(())
+    })
+}
+
+/// Checks the items in `task`'s note whose tasks have been completed, saving the note if that
+/// changed it. Returns the contents of the note.
+pub fn refresh_note(notes: &NoteStore, backend: &dyn TaskBackend, task: &Task) -> Result<String> {
+    let contents = notes.load(task)?;
+    let refreshed = refresh(backend, &contents)?;
+    if refreshed != contents {
+        notes.save(task, &refreshed)?;
+    }
+    Ok(refreshed)
+}
+
+/// Syncs the checklist in `contents`, which is `parent`'s note, with the child tasks. New
+/// unchecked items get tasks which `parent` depends on, checked items complete their tasks, and
+/// completed tasks check their items. Returns the new contents of the note, and the child tasks
+/// which need to be written, i.e. new tasks and completed ones; `parent` is updated in place.
+/// Nothing is written here, so that the children can go into the same [Transaction] as
+/// `parent`, and the note should only be saved once that's been committed.
+///
+/// [Transaction]: crate::taskwarrior::Transaction
+pub fn sync(
+    backend: &dyn TaskBackend,
+    parent: &mut Task,
+    contents: &str,
+) -> Result<(String, Vec<Task>)> {
+    let mut children = children(backend, contents)?;
+    let mut changed = Vec::new();
+    let contents = rewrite(contents, |item| {
+        match item.uuid.as_ref().and_then(|uuid| children.remove(uuid)) {
+            Some(child) if child.status == "completed" => item.checked = true,
+            Some(mut child) if item.checked && child.status != "deleted" => {
+                child.status = "completed".to_string();
+                child.end = Some(ParsableDateTime::from(Local::now()));
+                changed.push(child);
+            }
+            Some(_) => {}
+            // the task this item was for no longer exists, so leave the item alone
+            None if item.uuid.is_some() => {}
+            None if !item.checked && !item.text.is_empty() => {
+                // the item becomes the description as is, rather than going through `task add`,
+                // so that e.g. "ask about project:acme" doesn't set the child's project
+                let child = Task {
+                    project: parent.project.clone(),
+                    ..Task::new(&item.text)
+                };
+                parent
+                    .depends
+                    .get_or_insert_with(Vec::new)
+                    .push(child.uuid.clone());
+                item.uuid = Some(child.uuid.clone());
+                changed.push(child);
+            }
+            None => {}
+        }
+        Ok(())
+    })?;
+    Ok((contents, changed))
+}
+
+/// Looks up the tasks for the items in `contents`, keyed by UUID.
+fn children(backend: &dyn TaskBackend, contents: &str) -> Result<BTreeMap<String, Task>> {
+    let uuids = contents
+        .lines()
+        .filter_map(Item::parse)
+        .filter_map(|item| item.uuid)
+        .collect::<Vec<String>>();
+    if uuids.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    Task::get_by_uuid(backend, &uuids)
+}
+
+/// Passes every item in `contents` to `update`, and rewrites the lines of the items it changed.
+fn rewrite<F>(contents: &str, mut update: F) -> Result<String>
+where
+    F: FnMut(&mut Item) -> Result<()>,
+{
+    let mut rewritten = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let (text, newline) = match line.strip_suffix("\r\n") {
+            Some(text) => (text, "\r\n"),
+            None => match line.strip_suffix('\n') {
+                Some(text) => (text, "\n"),
+                None => (line, ""),
+            },
+        };
+        match Item::parse(text) {
+            Some(item) => {
+                let mut updated = item.clone();
+                update(&mut updated)?;
+                if updated == item {
+                    rewritten.push_str(text);
+                } else {
+                    rewritten.push_str(&updated.to_string());
+                }
+            }
+            None => rewritten.push_str(text),
+        }
+        rewritten.push_str(newline);
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::task;
+
+    #[test]
+    fn test_sync() -> Result<()> {
+        let mut parent = Task {
+            project: Some("home".to_string()),
+            ..task(1).with_description("plan the party")
+        };
+        let backend = MemoryBackend::new(vec![parent.clone()]);
+        let note = "# plan\n- [ ] book the venue project:work +next\n  * [x] pick a date\n- [ ] \nnot [ ] an item\n";
+        assert_eq!(progress(note), Some((1, 3)));
+
+        // the new task is only returned, not written
+        let (note, changed) = sync(&backend, &mut parent, note)?;
+        assert_eq!(backend.tasks().len(), 1);
+        assert_eq!(changed.len(), 1);
+        let venue = changed[0].clone();
+        assert_eq!(venue.description, "book the venue project:work +next");
+        assert_eq!(venue.project.as_deref(), Some("home"));
+        assert_eq!(venue.tags, None);
+        assert_eq!(parent.depends, Some(vec![venue.uuid.clone()]));
+        assert_eq!(
+            note,
+            format!(
+                "# plan\n- [ ] book the venue project:work +next <!-- taskn:{} -->\n  * [x] pick a date\n- [ ] \nnot [ ] an item\n",
+                venue.uuid
+            )
+        );
+        backend.import(&changed)?;
+
+        // syncing again changes nothing
+        let (synced, changed) = sync(&backend, &mut parent, &note)?;
+        assert_eq!(synced, note);
+        assert!(changed.is_empty());
+
+        // checking the item completes the task
+        let checked = note.replace("- [ ] book", "- [x] book");
+        let (_, changed) = sync(&backend, &mut parent, &checked)?;
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].status, "completed");
+
+        // and completing the task checks the item
+        backend.import(&changed)?;
+        assert_eq!(refresh(&backend, &note)?, checked);
+        Ok(())
+    }
+}
@@ -0,0 +1,19 @@
+use std::io;
+
+use structopt::clap::Shell;
+use structopt::StructOpt;
+
+use crate::error::Result;
+use crate::opt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+    pub shell: Shell,
+}
+
+/// Prints a completion script, e.g. `taskn completions zsh > ~/.zfunc/_taskn`.
+pub fn execute(args: Args) -> Result<()> {
+    opt::write_completions(args.shell, &mut io::stdout());
+    Ok(())
+}
@@ -1,88 +1,126 @@
-use std::fs::{create_dir_all, File};
-use std::io::{self, BufRead, BufReader};
 use std::path::PathBuf;
-use std::process::{exit, Command};
+use std::process::Command;
 
+use structopt::StructOpt;
+
+use crate::checklist;
+use crate::commands::print_plan;
+use crate::error::{Result, TasknError};
+use crate::notes::NoteStore;
 use crate::opt::Opt;
-use crate::taskwarrior::Task;
+use crate::taskwarrior::{Task, TaskBackend, Transaction};
+
+#[derive(Default, StructOpt)]
+pub struct Args {
+    /// Prints the changes that would be made to the tasks' tags instead of making them.
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// The template to start new notes from, out of the templates directory in `--root-dir`. By
+    /// default, templates are picked by the task's project and tags.
+    #[structopt(long)]
+    pub template: Option<String>,
+
+    /// Passed along to taskwarrior while selecting tasks.
+    pub filter: Vec<String>,
+}
 
-pub fn execute(opt: Opt) -> io::Result<()> {
-    let tasks = Task::get(opt.args.iter())?;
+pub fn execute(opt: Opt, args: Args, backend: &dyn TaskBackend) -> Result<()> {
+    let tasks = Task::get(backend, args.filter.iter())?;
+    let description = format!("edit {}", args.filter.join(" "));
+    edit_tasks(&opt, &args, backend, tasks, description.trim())
+}
 
-    if create_dir_all(&opt.root_dir).is_err() {
-        eprintln!("Failed to create taskn directory '{}'", &opt.root_dir);
-        exit(1)
+/// Opens the notes for `tasks` in the editor, and then writes any changes to the tasks that
+/// editing their notes calls for. `description` describes the operation in the journal.
+pub fn edit_tasks(
+    opt: &Opt,
+    args: &Args,
+    backend: &dyn TaskBackend,
+    mut tasks: Vec<Task>,
+    description: &str,
+) -> Result<()> {
+    let notes = opt.notes();
+    notes.create_root_dir()?;
+    for task in tasks.iter() {
+        notes.create(task, args.template.as_deref())?;
+        notes.refresh_front_matter(task, opt.front_matter)?;
+        if opt.checklists {
+            checklist::refresh_note(&notes, backend, task)?;
+        }
     }
 
     let status = Command::new(&opt.editor)
         .args(
             tasks
                 .iter()
-                .map(|task| task_path(&opt, task))
+                .map(|task| notes.path(task))
                 .collect::<Vec<PathBuf>>(),
         )
-        .status()?;
+        .status()
+        .map_err(|source| TasknError::Spawn {
+            program: opt.editor.clone(),
+            source,
+        })?;
     if !status.success() {
-        eprintln!("Failed to open editor '{}' ", &opt.editor);
-        exit(1)
+        return Err(TasknError::Exit {
+            program: opt.editor.clone(),
+            status,
+            stderr: String::new(),
+        });
     }
 
-    for task in tasks.iter() {
-        let has_note = task_has_note(&opt, task)?;
-        let has_tag = task.has_tag("taskn");
-
-        let action = if has_note && !has_tag {
-            Some("+taskn")
-        } else if !has_note && has_tag {
-            Some("-taskn")
-        } else {
-            None
-        };
-
-        if let Some(action) = action {
-            let status = Command::new("task")
-                .arg(&task.uuid)
-                .arg("modify")
-                .arg(action)
-                .output()?
-                .status;
-            if !status.success() {
-                eprintln!("Failed to annotate task '{}' with taskn status", task.id);
-                exit(1)
-            }
-        }
+    if args.dry_run {
+        let (transaction, _) = changes(&notes, backend, &mut tasks, opt.checklists)?;
+        print_plan(&transaction.plan(backend)?);
+        return Ok(());
     }
 
+    opt.journal().record(backend, description, |backend| {
+        let (transaction, synced) = changes(&notes, backend, &mut tasks, opt.checklists)?;
+        let planned = transaction.commit(backend)?;
+        // the notes point at the new checklist tasks, so they can only be saved once those exist
+        for (task, contents) in synced.iter() {
+            notes.save(task, contents)?;
+        }
+        Ok(planned)
+    })?;
     Ok(())
 }
 
-fn task_has_note(opt: &Opt, task: &Task) -> io::Result<bool> {
-    // a lot of editors will keep an "empty" line at the top of a file, so a naive 'byte size
-    // == 0' check won't cut it.
-    //
-    // because we expect notes to be VERY small (on the order of KB at most), we can just scan
-    // to see if there's any non-whitespace.
-    //
-    // NOTE: if perf becomes an issue, this will become a good place to refactor
-    let file = match File::open(task_path(opt, task)) {
-        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
-        Err(e) => return Err(e),
-        Ok(file) => file,
-    };
-    let reader = BufReader::new(file);
-    for line in reader.lines() {
-        for c in line?.chars() {
-            if !c.is_whitespace() {
-                return Ok(true);
+/// Collects the changes to make to `tasks` now that their notes have been edited: edits to their
+/// front matter, the `+taskn` tag, and (when `checklists` is set) the tasks for checklist items.
+/// Also returns the notes whose checklists were synced, with their new contents, which are to be
+/// saved once the transaction has been committed.
+fn changes(
+    notes: &NoteStore,
+    backend: &dyn TaskBackend,
+    tasks: &mut [Task],
+    checklists: bool,
+) -> Result<(Transaction, Vec<(Task, String)>)> {
+    let mut transaction = Transaction::new();
+    let mut synced_notes = Vec::new();
+    for task in tasks.iter_mut() {
+        let mut changed = match notes.front_matter(task)? {
+            Some(front_matter) => front_matter.apply(task)?,
+            None => false,
+        };
+        if checklists {
+            let contents = notes.load(task)?;
+            let depends = task.depends.clone();
+            let (synced, children) = checklist::sync(backend, task, &contents)?;
+            if synced != contents {
+                synced_notes.push((task.clone(), synced));
+            }
+            for child in children {
+                transaction.update(child);
             }
+            changed |= task.depends != depends;
+        }
+        changed |= notes.sync_tag(task)?;
+        if changed {
+            transaction.update(task.clone());
         }
     }
-    Ok(false)
-}
-
-fn task_path(opt: &Opt, task: &Task) -> PathBuf {
-    PathBuf::new()
-        .join(&opt.root_dir)
-        .join(&task.uuid)
-        .with_extension(&opt.file_format)
+    Ok((transaction, synced_notes))
 }
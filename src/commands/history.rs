@@ -0,0 +1,35 @@
+use structopt::StructOpt;
+
+use crate::error::Result;
+use crate::opt::Opt;
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// How many operations to list.
+    #[structopt(default_value = "20")]
+    pub count: usize,
+}
+
+/// Lists the most recent taskn operations, newest first.
+pub fn execute(opt: Opt, args: Args) -> Result<()> {
+    let entries = opt.journal().entries()?;
+    let reverted = entries
+        .iter()
+        .filter_map(|entry| entry.reverts)
+        .collect::<Vec<u64>>();
+    for entry in entries.iter().rev().take(args.count) {
+        println!(
+            "{:>4}  {}  {}  ({} tasks){}",
+            entry.id,
+            entry.time.0.format("%Y-%m-%d %H:%M"),
+            entry.operation,
+            entry.changes.len(),
+            if reverted.contains(&entry.id) {
+                "  [undone]"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
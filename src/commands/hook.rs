@@ -0,0 +1,294 @@
+//! Runs taskn as a Taskwarrior hook (see `taskn init`), so that notes follow their tasks without
+//! anyone having to run `taskn edit`. Taskwarrior passes the task(s) as JSON lines on stdin and
+//! expects the (possibly modified) task back on stdout, followed by any feedback for the user.
+//!
+//! A hook which fails stops Taskwarrior from saving the task, so hooks fail open instead: if
+//! anything goes wrong, from a broken configuration file to a task taskn can't read, the task is
+//! passed back unchanged and the error is printed to stderr.
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use structopt::StructOpt;
+
+use crate::error::{Result, TasknError};
+use crate::notes::NoteStore;
+use crate::opt::Opt;
+use crate::taskwarrior::Task;
+
+#[derive(StructOpt)]
+pub struct Args {
+    #[structopt(possible_values = &["on-add", "on-modify"])]
+    pub event: Event,
+
+    /// Why taskn couldn't be set up to run the hook (e.g. the configuration file is invalid), in
+    /// which case the task is passed back unchanged.
+    #[structopt(skip)]
+    pub setup_error: Option<TasknError>,
+}
+
+/// The Taskwarrior hooks taskn runs as.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    OnAdd,
+    OnModify,
+}
+
+impl FromStr for Event {
+    type Err = TasknError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "on-add" => Ok(Event::OnAdd),
+            "on-modify" => Ok(Event::OnModify),
+            _ => Err(TasknError::Invalid(format!(
+                "unsupported hook '{}', expected on-add or on-modify",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::OnAdd => write!(f, "on-add"),
+            Event::OnModify => write!(f, "on-modify"),
+        }
+    }
+}
+
+/// Unlike the other commands, this doesn't take a backend: hooks run in the middle of a
+/// Taskwarrior command, so they must not call back into Taskwarrior.
+pub fn execute(opt: Opt, args: Args) -> Result<()> {
+    let stdin = io::stdin();
+    let lines = stdin.lock().lines().collect::<io::Result<Vec<String>>>()?;
+    let result = match args.setup_error {
+        Some(e) => Err(e),
+        None => run(&opt.notes(), args.event, &lines),
+    };
+
+    let stdout = io::stdout();
+    respond(result, &lines, &mut stdout.lock(), &mut io::stderr())?;
+    Ok(())
+}
+
+/// Runs the hook on the task(s) Taskwarrior passed in `lines`, returning the task to pass back as
+/// JSON, along with any feedback for the user.
+fn run(notes: &NoteStore, event: Event, lines: &[String]) -> Result<(String, Option<String>)> {
+    let mut lines = lines.iter();
+    let mut read_task = || -> Result<Task> {
+        let line = lines.next().ok_or_else(|| {
+            TasknError::Invalid(format!("taskwarrior did not pass a task to {}", event))
+        })?;
+        serde_json::from_str(line).map_err(|source| TasknError::Decode {
+            context: format!("the task passed to {}", event),
+            source,
+        })
+    };
+
+    let (task, feedback) = match event {
+        Event::OnAdd => (on_add(notes, read_task()?)?, None),
+        Event::OnModify => {
+            let original = read_task()?;
+            on_modify(notes, &original, read_task()?)?
+        }
+    };
+
+    let json = serde_json::to_string(&task).map_err(|source| TasknError::Decode {
+        context: format!("task {} for taskwarrior", task.uuid),
+        source,
+    })?;
+    Ok((json, feedback))
+}
+
+/// Writes the hook's response to `out`: the task to save, followed by any feedback. If the hook
+/// failed, the task Taskwarrior passed in (the last of `lines`) goes back unchanged instead, and
+/// the error goes to `err`.
+fn respond(
+    result: Result<(String, Option<String>)>,
+    lines: &[String],
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> io::Result<()> {
+    match result {
+        Ok((json, feedback)) => {
+            writeln!(out, "{}", json)?;
+            if let Some(feedback) = feedback {
+                writeln!(out, "{}", feedback)?;
+            }
+        }
+        Err(e) => {
+            if let Some(line) = lines.last() {
+                writeln!(out, "{}", line)?;
+            }
+            writeln!(
+                err,
+                "taskn: {} (the task was saved without taskn's changes)",
+                e
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Starts the new task's note from its template, if one applies (see [crate::templates]), and
+/// makes sure `+taskn` is only set when the task has a note. The latter matters for `task
+/// duplicate`, which copies the tag but not the note.
+fn on_add(notes: &NoteStore, mut task: Task) -> Result<Task> {
+    notes.create(&task, None)?;
+    notes.sync_tag(&mut task)?;
+    Ok(task)
+}
+
+/// Moves a task's note into the trash when the task is deleted, and back out again if the task
+/// is restored. Completing a task keeps its note where it is, so that it can still be opened and
+/// searched, unless nothing was ever written in it; then it goes into the trash too, and comes
+/// back if the task is reopened. Returns the task along with a message for the user, if anything
+/// happened.
+fn on_modify(notes: &NoteStore, original: &Task, mut task: Task) -> Result<(Task, Option<String>)> {
+    let was_deleted = original.status == "deleted";
+    let is_deleted = task.status == "deleted";
+    let was_completed = original.status == "completed";
+    let is_completed = task.status == "completed";
+
+    let path = notes.path(&task);
+    let trash_path = notes.trash_path(&task);
+
+    let mut feedback = None;
+    if is_deleted && !was_deleted && path.exists() {
+        notes.move_note(&path, &trash_path)?;
+        feedback = Some(format!("taskn: moved note to {}", trash_path.display()));
+    } else if is_completed && !was_completed && path.exists() && !notes.has_note(&task)? {
+        notes.move_note(&path, &trash_path)?;
+        feedback = Some(format!(
+            "taskn: moved unused note to {}",
+            trash_path.display()
+        ));
+    } else if (was_deleted && !is_deleted || was_completed && !is_completed)
+        && trash_path.exists()
+        && !path.exists()
+    {
+        notes.move_note(&trash_path, &path)?;
+        feedback = Some(format!("taskn: restored note {}", path.display()));
+    }
+
+    // deleted tasks keep their tag, so that it's clear they had a note when they're restored
+    if !is_deleted {
+        notes.sync_tag(&mut task)?;
+    }
+    Ok((task, feedback))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, create_dir_all};
+
+    use super::*;
+    use crate::test_support::{self, TempDir};
+
+    fn task(status: &str, tags: &[&str]) -> Task {
+        test_support::task(1)
+            .with_description("write the report")
+            .with_status(status)
+            .with_tags(tags)
+    }
+
+    #[test]
+    fn test_on_modify_trashes_and_restores_notes() -> Result<()> {
+        let root_dir = TempDir::create("hook")?;
+        let notes = NoteStore::new(root_dir.path(), "md");
+        let path = notes.path(&task("pending", &[]));
+        fs::write(&path, "some notes\n")?;
+
+        let (deleted, feedback) = on_modify(
+            &notes,
+            &task("pending", &["taskn"]),
+            task("deleted", &["taskn"]),
+        )?;
+        assert!(!path.exists() && feedback.is_some());
+        assert!(deleted.has_tag("taskn"));
+
+        let (restored, _) = on_modify(&notes, &deleted, task("pending", &["taskn"]))?;
+        assert!(path.exists() && restored.has_tag("taskn"));
+
+        // duplicates carry the tag over, but not the note
+        fs::remove_file(&path)?;
+        let duplicate = on_add(&notes, task("pending", &["home", "taskn"]))?;
+        assert_eq!(duplicate.tags, Some(vec!["home".to_string()]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_add_starts_notes_from_templates() -> Result<()> {
+        let root_dir = TempDir::new("hook-add");
+        let notes = NoteStore::new(root_dir.path(), "md");
+        create_dir_all(notes.templates().dir())?;
+        fs::write(notes.templates().path("default"), "# {{description}}\n")?;
+
+        let added = on_add(&notes, task("pending", &[]))?;
+        assert_eq!(notes.load(&added)?, "# write the report\n");
+        assert!(!added.has_tag("taskn"));
+
+        // the note is still untouched after the task changes, even though the template would
+        // render differently now
+        let mut renamed = added.clone();
+        renamed.description = "write the summary".to_string();
+        assert!(!notes.has_note(&renamed)?);
+        fs::write(notes.path(&renamed), "# write the report\nfirst draft\n")?;
+        assert!(notes.has_note(&renamed)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_modify_completes_tasks() -> Result<()> {
+        let root_dir = TempDir::create("hook-done")?;
+        let notes = NoteStore::new(root_dir.path(), "md");
+        let path = notes.path(&task("pending", &[]));
+
+        // notes with something in them stay put
+        fs::write(&path, "some notes\n")?;
+        let (completed, feedback) = on_modify(
+            &notes,
+            &task("pending", &["taskn"]),
+            task("completed", &["taskn"]),
+        )?;
+        assert!(path.exists() && feedback.is_none());
+        assert!(completed.has_tag("taskn"));
+
+        // while empty ones are put away, until the task is reopened
+        fs::write(&path, "\n\n")?;
+        let (completed, feedback) =
+            on_modify(&notes, &task("pending", &[]), task("completed", &[]))?;
+        assert!(!path.exists() && feedback.is_some());
+        assert!(!completed.has_tag("taskn"));
+
+        on_modify(&notes, &completed, task("pending", &[]))?;
+        assert!(path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hooks_fail_open() -> Result<()> {
+        let root_dir = TempDir::new("hook-fail");
+        let notes = NoteStore::new(root_dir.path(), "md");
+        let original = serde_json::to_string(&task("pending", &[])).unwrap();
+        for line in [
+            "{\"uuid\": \"2f3a4c7e",
+            r#"{"uuid": "2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2", "description": "x", "status": "pending", "estimate": 1.5}"#,
+        ]
+        .iter()
+        {
+            let lines = vec![original.clone(), line.to_string()];
+            let result = run(&notes, Event::OnModify, &lines);
+            assert!(result.is_err());
+
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            respond(result, &lines, &mut out, &mut err)?;
+            assert_eq!(String::from_utf8_lossy(&out), format!("{}\n", line));
+            assert!(String::from_utf8_lossy(&err).starts_with("taskn: "));
+        }
+        assert!(!root_dir.path().exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::env;
+use std::fs::{self, create_dir_all};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+use shellexpand::tilde;
+
+use crate::error::{Result, TasknError};
+use crate::opt::Opt;
+use crate::ordering::List;
+use crate::taskwarrior::{TaskBackend, Taskrc};
+
+const HOOKS: &[&str] = &["on-add", "on-modify"];
+
+/// Defines the UDAs taskn uses and installs the scripts that run `taskn hook` into Taskwarrior's
+/// hooks directory. Running it again overwrites the scripts, e.g. to pick up a different
+/// `--root-dir` or `--profile`.
+pub fn execute(opt: Opt, backend: &dyn TaskBackend) -> Result<()> {
+    let mut taskrc = Taskrc::load(backend)?;
+    taskrc.define_uda("taskn_reminder_uuid", "string", None)?;
+    List::default().define_uda(backend)?;
+
+    let hooks_dir = hooks_dir(&taskrc);
+    create_dir_all(&hooks_dir).map_err(|source| TasknError::Note {
+        path: hooks_dir.clone(),
+        source,
+    })?;
+
+    let program = env::current_exe()?;
+    for hook in HOOKS.iter() {
+        let path = hooks_dir.join(format!("{}.taskn", hook));
+        let script = hook_script(&opt, &program.to_string_lossy(), hook);
+
+        let note_error = |source| TasknError::Note {
+            path: path.clone(),
+            source,
+        };
+        fs::write(&path, script).map_err(note_error)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).map_err(note_error)?;
+        println!("installed {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// The script for `hook`, which runs `program` with the options taskn is running with now, so that
+/// the hook uses the same notes and the same configuration.
+fn hook_script(opt: &Opt, program: &str, hook: &str) -> String {
+    let mut args = vec![
+        shell_quote(program),
+        format!("--root-dir {}", shell_quote(&opt.root_dir)),
+        format!("--file-format {}", shell_quote(&opt.file_format)),
+    ];
+    if let Some(config) = &opt.config {
+        args.push(format!("--config {}", shell_quote(config)));
+    }
+    if let Some(profile) = &opt.profile {
+        args.push(format!("--profile {}", shell_quote(profile)));
+    }
+    format!(
+        "#!/bin/sh\n# Installed by `taskn init`.\nexec {} hook {}\n",
+        args.join(" "),
+        hook
+    )
+}
+
+/// Finds the directory Taskwarrior loads hooks from: `hooks.location` if it's set, and otherwise
+/// `hooks/` inside of the data directory.
+fn hooks_dir(taskrc: &Taskrc) -> PathBuf {
+    if let Some(hooks_location) = taskrc.get("hooks.location") {
+        return PathBuf::from(tilde(hooks_location).as_ref());
+    }
+
+    let data_location = taskrc.get("data.location").unwrap_or("~/.task");
+    PathBuf::from(tilde(data_location).as_ref()).join("hooks")
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_script_keeps_options() {
+        let opt = Opt {
+            root_dir: "/home/me/notes".to_string(),
+            file_format: "md".to_string(),
+            config: Some("/home/me/taskn.toml".to_string()),
+            profile: Some("work".to_string()),
+            ..Opt::default()
+        };
+        assert_eq!(
+            hook_script(&opt, "/usr/bin/taskn", "on-add"),
+            "#!/bin/sh\n# Installed by `taskn init`.\nexec '/usr/bin/taskn' --root-dir '/home/me/notes' \
+             --file-format 'md' --config '/home/me/taskn.toml' --profile 'work' hook on-add\n"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Interactive mode keeps its tasks in memory for as long as it's open, so by the time it flushes
+//! them another terminal (or a sync) may have changed the same tasks. Before writing, each task is
+//! compared against what's in Taskwarrior now, and tasks which changed on both sides are returned
+//! as [Conflict]s rather than being overwritten.
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{Difference, Task, TaskBackend};
+
+/// A task which was changed both by interactive mode and elsewhere since it was loaded.
+pub struct Conflict {
+    /// The task as it was when interactive mode loaded it.
+    pub base: Task,
+    /// The task with interactive mode's changes.
+    pub mine: Task,
+    /// The task as it is in Taskwarrior now.
+    pub theirs: Task,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Resolution {
+    KeepMine,
+    KeepTheirs,
+    /// Applies the attributes interactive mode changed on top of the current task.
+    Merge,
+}
+
+impl Conflict {
+    /// Returns the task to write for this conflict.
+    pub fn resolve(&self, resolution: Resolution) -> Result<Task> {
+        match resolution {
+            Resolution::KeepMine => Ok(self.mine.clone()),
+            Resolution::KeepTheirs => Ok(self.theirs.clone()),
+            Resolution::Merge => {
+                let mut merged = self.theirs.attributes()?;
+                for difference in self.base.diff(&self.mine)? {
+                    match difference.after {
+                        Some(value) => merged.insert(difference.name, value),
+                        None => merged.remove(&difference.name),
+                    };
+                }
+                serde_json::from_value(Value::Object(merged)).map_err(|source| TasknError::Decode {
+                    context: format!("the merged task {}", self.mine.uuid),
+                    source,
+                })
+            }
+        }
+    }
+
+    /// Lists the attributes which differ between mine (before) and theirs (after).
+    pub fn differences(&self) -> Result<Vec<Difference>> {
+        Ok(self
+            .mine
+            .diff(&self.theirs)?
+            .into_iter()
+            .filter(|difference| difference.name != "modified")
+            .collect())
+    }
+}
+
+/// Compares the tasks interactive mode is about to write (`edited`) against the tasks as they
+/// were loaded (`loaded`) and as they are in Taskwarrior now. Returns the tasks which can be
+/// written as-is, and the tasks which conflict. Tasks which interactive mode didn't change are
+/// left out entirely, as are tasks which no longer exist.
+pub fn detect(
+    backend: &dyn TaskBackend,
+    loaded: &[Task],
+    edited: &[Task],
+) -> Result<(Vec<Task>, Vec<Conflict>)> {
+    let loaded = loaded
+        .iter()
+        .map(|task| (task.uuid.as_str(), task))
+        .collect::<BTreeMap<&str, &Task>>();
+
+    let mut changed_tasks = Vec::new();
+    for task in edited.iter() {
+        match loaded.get(task.uuid.as_str()) {
+            Some(base) if same_version(base, task)? => {}
+            base => changed_tasks.push((base.copied(), task)),
+        }
+    }
+    if changed_tasks.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let uuids = changed_tasks
+        .iter()
+        .map(|(_, task)| task.uuid.as_str())
+        .collect::<Vec<&str>>();
+    let mut current = Task::get_by_uuid(backend, &uuids)?;
+
+    let mut changes = Vec::new();
+    let mut conflicts = Vec::new();
+    for (base, mine) in changed_tasks.into_iter() {
+        let theirs = match current.remove(&mine.uuid) {
+            None => continue,
+            Some(theirs) => theirs,
+        };
+        match base {
+            Some(base) if !same_version(base, &theirs)? => conflicts.push(Conflict {
+                base: base.clone(),
+                mine: mine.clone(),
+                theirs,
+            }),
+            _ => changes.push(mine.clone()),
+        }
+    }
+    Ok((changes, conflicts))
+}
+
+/// Determines whether two copies of a task hold the same data. This compares the `modified`
+/// timestamps as well as the contents, since `modified` only has a resolution of one second.
+fn same_version(a: &Task, b: &Task) -> Result<bool> {
+    Ok(a.modified == b.modified && a.attributes()? == b.attributes()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::task;
+
+    #[test]
+    fn test_detect_and_merge() -> Result<()> {
+        let loaded = vec![
+            task(1).with_order(0.0),
+            task(2).with_order(1.0),
+            task(3).with_order(2.0),
+        ];
+        // another terminal renames task 1 and completes task 3
+        let renamed = task(1)
+            .with_order(0.0)
+            .with_description("renamed")
+            .with_modified("20210102T000000Z");
+        let completed = task(3).with_order(2.0).with_status("completed");
+        let backend = MemoryBackend::new(vec![renamed, task(2).with_order(1.0), completed]);
+
+        // interactive mode moves task 1 to the end, which changes every task's position
+        let edited = vec![
+            task(2).with_order(0.0),
+            task(3).with_order(1.0),
+            task(1).with_order(2.0),
+        ];
+        let (changes, conflicts) = detect(&backend, &loaded, &edited)?;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].uuid, task(2).uuid);
+        assert_eq!(conflicts.len(), 2);
+
+        let conflict = &conflicts[1];
+        assert_eq!(conflict.mine.uuid, task(1).uuid);
+        assert_eq!(
+            conflict
+                .differences()?
+                .iter()
+                .map(|difference| difference.to_string())
+                .collect::<Vec<String>>(),
+            vec!["description: task 1 -> renamed", "taskn_order: 2 -> 0"]
+        );
+        let merged = conflict.resolve(Resolution::Merge)?;
+        assert_eq!(merged.description, "renamed");
+        assert_eq!(merged.taskn_order, Some(2.0));
+        assert_eq!(
+            conflict.resolve(Resolution::KeepMine)?.description,
+            "task 1"
+        );
+        assert_eq!(
+            conflict.resolve(Resolution::KeepTheirs)?.taskn_order,
+            Some(0.0)
+        );
+
+        let (changes, conflicts) = detect(&backend, &loaded, &loaded)?;
+        assert!(changes.is_empty() && conflicts.is_empty());
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
+mod conflict;
 mod events;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::{self, Stdout};
 
+use structopt::StructOpt;
 use termion::event::Key;
 use termion::raw::{IntoRawMode, RawTerminal};
 use tui::backend::TermionBackend;
@@ -10,25 +13,39 @@ use tui::style::{Modifier, Style};
 use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
 use tui::Terminal;
 
+use crate::checklist;
+use crate::error::Result;
 use crate::opt::Opt;
-use crate::taskwarrior::Task;
+use crate::ordering;
+use crate::search::{self, Index, Query};
+use crate::taskwarrior::{Task, TaskBackend, Transaction};
+use conflict::{Conflict, Resolution};
 use events::{Event, Events};
 
 type Term = Terminal<TermionBackend<RawTerminal<Stdout>>>;
 
-pub fn execute(opt: Opt) -> io::Result<()> {
-    let stdout = io::stdout().into_raw_mode()?;
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+#[derive(StructOpt)]
+pub struct Args {
+    /// The named ordering to show and reorder. Defaults to the configured list whose filter is
+    /// the filter provided, or whose Taskwarrior context is active.
+    #[structopt(long)]
+    pub list: Option<String>,
+
+    /// Passed along to taskwarrior while selecting tasks.
+    pub filter: Vec<String>,
+}
 
-    let mut taskwarrior_args = opt.args.clone();
-    taskwarrior_args.push("(status:pending or status:waiting)".to_string());
+pub fn execute(opt: Opt, args: Args, backend: &dyn TaskBackend) -> Result<()> {
+    let list = opt.list(backend, args.list.as_deref(), &args.filter)?;
+
+    let stdout = io::stdout().into_raw_mode()?;
+    let mut terminal = Terminal::new(TermionBackend::new(stdout))?;
 
     // clear screen
     println!("\0{}[2J", 27 as char);
 
     let events = Events::new();
-    let mut common_state = CommonState::load_from_taskwarrior(&opt)?;
+    let mut common_state = CommonState::load_from_taskwarrior(&opt, list, backend)?;
     let mut mode: Box<dyn Mode> = Box::new(Normal);
     loop {
         mode.render(&mut common_state, &mut terminal)?;
@@ -41,9 +58,20 @@ pub fn execute(opt: Opt) -> io::Result<()> {
                         mode = new_mode;
                     }
                     if result.should_flush {
-                        common_state = common_state.flush_to_taskwarrior(&opt)?;
+                        let (changes, conflicts) = common_state.changes(backend)?;
+                        if conflicts.is_empty() {
+                            common_state =
+                                common_state.flush_to_taskwarrior(&opt, backend, changes)?;
+                        } else {
+                            mode = Box::new(Resolve::new(conflicts));
+                        }
                     } else if result.should_load {
-                        common_state = CommonState::load_from_taskwarrior(&opt)?;
+                        let filter = common_state.filter.take();
+                        common_state =
+                            CommonState::load_from_taskwarrior(&opt, common_state.list, backend)?;
+                        if let Some(filter) = filter {
+                            common_state.set_filter(filter.query);
+                        }
                     }
                 }
             },
@@ -55,52 +83,148 @@ pub fn execute(opt: Opt) -> io::Result<()> {
 }
 
 struct CommonState {
+    /// The ordering which the tasks are shown in and which reordering them changes.
+    list: ordering::List,
     list_state: ListState,
     tasks: Vec<Task>,
+    /// The tasks as they were loaded, to tell which tasks were changed elsewhere since.
+    loaded: Vec<Task>,
+    /// Tasks whose conflicts have been resolved, keyed by UUID, to write in place of [tasks].
+    resolved: BTreeMap<String, Task>,
     // TODO: right now we represent the contents of a task on this [CommonState]
     // but it seems like it ought to be on the task instead, since it's specifically
     // that task's contents
     // think about moving this onto the [Task].
     tasks_contents: Vec<(String, String)>,
+    /// The search index for the notes, for filtering the tasks by their notes.
+    index: Index,
+    filter: Option<TaskFilter>,
+}
+
+/// Narrows the tasks shown down to those whose notes match a search query.
+struct TaskFilter {
+    query: String,
+    /// The UUIDs of the matching tasks, or `None` while the query has no words in it.
+    matching: Option<BTreeSet<String>>,
 }
 
 impl CommonState {
-    fn load_from_taskwarrior(opt: &Opt) -> io::Result<Self> {
-        let mut tasks = Task::get(["status:pending"].iter())?;
-        tasks.sort_by(|a, b| a.estimate.partial_cmp(&b.estimate).unwrap());
+    fn load_from_taskwarrior(
+        opt: &Opt,
+        list: ordering::List,
+        backend: &dyn TaskBackend,
+    ) -> Result<Self> {
+        let tasks = list.load(backend)?;
 
         let mut list_state = ListState::default();
         if tasks.len() > 0 {
             list_state.select(Some(0));
         }
 
+        let notes = opt.notes();
         let mut tasks_contents = Vec::with_capacity(tasks.len());
         for task in tasks.iter() {
-            tasks_contents.push((task.uuid.clone(), task.load_contents(opt)?));
+            let contents = if opt.checklists {
+                checklist::refresh_note(&notes, backend, task)?
+            } else {
+                notes.load(task)?
+            };
+            tasks_contents.push((task.uuid.clone(), contents));
         }
 
         Ok(CommonState {
+            list,
             list_state,
+            loaded: tasks.clone(),
             tasks,
             tasks_contents,
+            resolved: BTreeMap::new(),
+            index: Index::open(&notes)?,
+            filter: None,
         })
     }
 
-    fn flush_to_taskwarrior(self, opt: &Opt) -> io::Result<Self> {
-        // need to calculate new_selected before into_iter()
-        // because otherwise it would partially move out of self
-        // and cause a compiler error
-        let mut new_selected = self.selected();
-        for (order, mut task) in self.tasks.into_iter().enumerate() {
-            task.estimate = Some(order as i32);
-            task.save()?;
+    /// Filters the tasks by `query`, moving the selection to the first task shown if the
+    /// selected task is filtered out.
+    fn set_filter(&mut self, query: String) {
+        let matching = Query::parse(&query)
+            .ok()
+            .map(|query| search::matching(&self.index, &query));
+        self.filter = Some(TaskFilter { query, matching });
+        if !self.is_visible(self.selected()) {
+            if let Some(first) = (0..self.tasks.len()).find(|i| self.is_visible(*i)) {
+                self.list_state.select(Some(first));
+            }
         }
-        let mut new_self = Self::load_from_taskwarrior(opt)?;
+    }
 
-        if new_selected >= new_self.tasks.len() {
-            new_selected = new_self.tasks.len() - 1;
+    /// Determines whether the task at `position` is shown, i.e. it isn't filtered out.
+    fn is_visible(&self, position: usize) -> bool {
+        let task = match self.tasks.get(position) {
+            None => return false,
+            Some(task) => task,
+        };
+        match self
+            .filter
+            .as_ref()
+            .and_then(|filter| filter.matching.as_ref())
+        {
+            None => true,
+            Some(matching) => matching.contains(&task.uuid),
+        }
+    }
+
+    /// Finds the closest task shown above (or below) the selected task.
+    fn neighbour(&self, up: bool) -> Option<usize> {
+        let selected = self.selected();
+        if up {
+            (0..selected).rev().find(|i| self.is_visible(*i))
+        } else {
+            (selected + 1..self.tasks.len()).find(|i| self.is_visible(*i))
+        }
+    }
+
+    /// Works out which tasks need to be written to apply the changes made in interactive mode.
+    /// Tasks which have also been changed outside of taskn since they were loaded are returned as
+    /// conflicts instead, unless they've already been resolved.
+    fn changes(&mut self, backend: &dyn TaskBackend) -> Result<(Vec<Task>, Vec<Conflict>)> {
+        self.list.rerank(&mut self.tasks);
+        let unresolved = self
+            .tasks
+            .iter()
+            .filter(|task| !self.resolved.contains_key(&task.uuid))
+            .cloned()
+            .collect::<Vec<Task>>();
+        let (mut changes, conflicts) = conflict::detect(backend, &self.loaded, &unresolved)?;
+        changes.extend(self.resolved.values().cloned());
+        Ok((changes, conflicts))
+    }
+
+    fn flush_to_taskwarrior(
+        self,
+        opt: &Opt,
+        backend: &dyn TaskBackend,
+        changes: Vec<Task>,
+    ) -> Result<Self> {
+        let selected = self.selected();
+        if !changes.is_empty() {
+            let list = &self.list;
+            opt.journal().record(backend, "interactive", |backend| {
+                // the ranks may be the first this list has, so its UDA has to exist
+                list.define_uda(backend)?;
+                changes.into_iter().collect::<Transaction>().commit(backend)
+            })?;
+        }
+        let mut new_self = Self::load_from_taskwarrior(opt, self.list, backend)?;
+
+        // keep the selection where it was, unless the list shrank below it (or emptied out)
+        let last = new_self.tasks.len().checked_sub(1);
+        new_self
+            .list_state
+            .select(last.map(|last| selected.min(last)));
+        if let Some(filter) = self.filter {
+            new_self.set_filter(filter.query);
         }
-        new_self.list_state.select(Some(new_selected));
         Ok(new_self)
     }
 
@@ -113,13 +237,19 @@ impl CommonState {
 
     fn selected_contents(&self) -> &str {
         let selected = self.selected();
+        if !self.is_visible(selected) {
+            return "";
+        }
         let selected_uuid = &self.tasks[selected].uuid;
-        for (uuid, contents) in self.tasks_contents.iter() {
-            if selected_uuid == uuid {
-                return contents;
-            }
+        match self
+            .tasks_contents
+            .iter()
+            .find(|(uuid, _)| uuid == selected_uuid)
+        {
+            Some((_, contents)) => contents,
+            // every loaded task has its contents, but show nothing rather than crash if not
+            None => "",
         }
-        panic!("selected invariant violated");
     }
 }
 
@@ -140,14 +270,14 @@ impl Default for ActionResult {
 }
 
 trait Mode {
-    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> io::Result<()>;
+    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> Result<()>;
 
     fn update(
         &mut self,
         opt: &Opt,
         common_state: &mut CommonState,
         key: Key,
-    ) -> io::Result<ActionResult>;
+    ) -> Result<ActionResult>;
 }
 
 /// The default interactive mode. Does not modify any data. Allows users to look through their
@@ -155,36 +285,44 @@ trait Mode {
 struct Normal;
 
 impl Mode for Normal {
-    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> io::Result<()> {
-        terminal.draw(|frame| common_render(frame, common_state, &[Modifier::DIM]))
+    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> Result<()> {
+        terminal.draw(|frame| common_render(frame, common_state, &[Modifier::DIM]))?;
+        Ok(())
     }
 
     fn update(
         &mut self,
-        _opt: &Opt,
+        opt: &Opt,
         common_state: &mut CommonState,
         key: Key,
-    ) -> io::Result<ActionResult> {
+    ) -> Result<ActionResult> {
         let selected = common_state.selected();
         match key {
-            Key::Up => {
-                if selected > 0 {
-                    common_state.list_state.select(Some(selected - 1));
+            key if opt.keys.up.contains(&key) || opt.keys.down.contains(&key) => {
+                if let Some(neighbour) = common_state.neighbour(opt.keys.up.contains(&key)) {
+                    common_state.list_state.select(Some(neighbour));
                 }
             }
-            Key::Down => {
-                if selected < common_state.tasks.len() - 1 {
-                    common_state.list_state.select(Some(selected + 1));
+            key if opt.keys.search.contains(&key) => {
+                if common_state.filter.is_none() {
+                    common_state.set_filter(String::new());
                 }
+                return Ok(ActionResult {
+                    new_mode: Some(Box::new(Filter)),
+                    should_flush: false,
+                    should_load: false,
+                });
             }
-            Key::Char('d') => {
+            // the rest act on the selected task, so there has to be one shown
+            _ if !common_state.is_visible(selected) => {}
+            key if opt.keys.done.contains(&key) => {
                 return Ok(ActionResult {
                     new_mode: Some(Box::new(Done)),
                     should_flush: false,
                     should_load: false,
                 })
             }
-            Key::Char('s') => {
+            key if opt.keys.shift.contains(&key) => {
                 return Ok(ActionResult {
                     new_mode: Some(Box::new(Shift::new(selected))),
                     should_flush: false,
@@ -216,41 +354,37 @@ impl Shift {
 }
 
 impl Mode for Shift {
-    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> io::Result<()> {
+    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> Result<()> {
         terminal.draw(|frame| {
             common_render(frame, common_state, &[Modifier::DIM, Modifier::UNDERLINED])
-        })
+        })?;
+        Ok(())
     }
 
     fn update(
         &mut self,
-        _opt: &Opt,
+        opt: &Opt,
         common_state: &mut CommonState,
         key: Key,
-    ) -> io::Result<ActionResult> {
+    ) -> Result<ActionResult> {
         match key {
-            Key::Up => {
+            key if opt.keys.up.contains(&key) || opt.keys.down.contains(&key) => {
+                // move past the neighbouring task shown, along with any filtered out in between
                 let selected = common_state.selected();
-                if selected > 0 {
-                    common_state.tasks.swap(selected, selected - 1);
-                    common_state.list_state.select(Some(selected - 1));
+                if let Some(neighbour) = common_state.neighbour(opt.keys.up.contains(&key)) {
+                    let task = common_state.tasks.remove(selected);
+                    common_state.tasks.insert(neighbour, task);
+                    common_state.list_state.select(Some(neighbour));
                 }
             }
-            Key::Down => {
-                let selected = common_state.selected();
-                if selected < common_state.tasks.len() - 1 {
-                    common_state.tasks.swap(selected, selected + 1);
-                    common_state.list_state.select(Some(selected + 1));
-                }
-            }
-            Key::Char('\n') | Key::Char('s') => {
+            key if opt.keys.confirm.contains(&key) || opt.keys.shift.contains(&key) => {
                 return Ok(ActionResult {
                     new_mode: Some(Box::new(Normal)),
                     should_flush: true,
                     should_load: false,
                 })
             }
-            Key::Esc | Key::Ctrl('f') => {
+            key if opt.keys.cancel.contains(&key) => {
                 let selected = common_state.selected();
                 let task = common_state.tasks.remove(selected);
                 common_state.tasks.insert(self.original_pos, task);
@@ -272,11 +406,59 @@ impl Mode for Shift {
     }
 }
 
+/// Filters the tasks down to those whose notes match a search query, as it's typed. Confirming
+/// keeps the filter in place, while cancelling clears it.
+struct Filter;
+
+impl Mode for Filter {
+    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> Result<()> {
+        terminal.draw(|frame| common_render(frame, common_state, &[Modifier::DIM]))?;
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        opt: &Opt,
+        common_state: &mut CommonState,
+        key: Key,
+    ) -> Result<ActionResult> {
+        let mut query = match common_state.filter.as_ref() {
+            None => String::new(),
+            Some(filter) => filter.query.clone(),
+        };
+        match key {
+            key if opt.keys.confirm.contains(&key) => {
+                if query.trim().is_empty() {
+                    common_state.filter = None;
+                }
+                return Ok(ActionResult {
+                    new_mode: Some(Box::new(Normal)),
+                    ..ActionResult::default()
+                });
+            }
+            key if opt.keys.cancel.contains(&key) => {
+                common_state.filter = None;
+                return Ok(ActionResult {
+                    new_mode: Some(Box::new(Normal)),
+                    ..ActionResult::default()
+                });
+            }
+            Key::Char(c) => query.push(c),
+            Key::Backspace => {
+                query.pop();
+            }
+            _ => return Ok(ActionResult::default()),
+        }
+        common_state.set_filter(query);
+        Ok(ActionResult::default())
+    }
+}
+
 /// Marks a task done as
 struct Done;
 
 impl Mode for Done {
-    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> io::Result<()> {
+    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> Result<()> {
         terminal.draw(|frame| {
             let layout = default_layout(frame);
             render_tasks(
@@ -290,27 +472,28 @@ impl Mode for Done {
             let paragraph = Paragraph::new(text)
                 .block(Block::default().title("Mark Done?").borders(Borders::ALL));
             frame.render_widget(paragraph, layout[1]);
-        })
+        })?;
+        Ok(())
     }
 
     fn update(
         &mut self,
-        _opt: &Opt,
+        opt: &Opt,
         common_state: &mut CommonState,
         key: Key,
-    ) -> io::Result<ActionResult> {
+    ) -> Result<ActionResult> {
         match key {
-            Key::Esc | Key::Ctrl('f') => {
+            key if opt.keys.cancel.contains(&key) => {
                 return Ok(ActionResult {
                     new_mode: Some(Box::new(Normal)),
                     should_flush: false,
                     should_load: false,
                 })
             }
-            Key::Char('\n') => {
+            key if opt.keys.confirm.contains(&key) => {
                 // TODO: mark the current highlighted task as done
                 let selected = common_state.selected();
-                common_state.tasks[selected].status = "done".to_string();
+                common_state.tasks[selected].status = "completed".to_string();
                 return Ok(ActionResult {
                     new_mode: Some(Box::new(Normal)),
                     should_flush: true,
@@ -323,6 +506,90 @@ impl Mode for Done {
     }
 }
 
+/// Shown when flushing finds tasks which were also changed outside of taskn since they were
+/// loaded. Each conflict is resolved in turn, and the flush carries on once they all are.
+/// Cancelling throws away every unsaved change and reloads the tasks.
+struct Resolve {
+    conflicts: Vec<Conflict>,
+    current: usize,
+}
+
+impl Resolve {
+    fn new(conflicts: Vec<Conflict>) -> Self {
+        Self {
+            conflicts,
+            current: 0,
+        }
+    }
+}
+
+impl Mode for Resolve {
+    fn render(&self, common_state: &mut CommonState, terminal: &mut Term) -> Result<()> {
+        let conflict = &self.conflicts[self.current];
+        if let Some(position) = common_state
+            .tasks
+            .iter()
+            .position(|task| task.uuid == conflict.mine.uuid)
+        {
+            common_state.list_state.select(Some(position));
+        }
+
+        let mut text = format!(
+            "'{}' was changed outside of taskn (mine -> theirs):\n\n",
+            conflict.theirs.description
+        );
+        for difference in conflict.differences()? {
+            text.push_str(&format!("{}\n", difference));
+        }
+        text.push_str("\nKEEP MINE (m), KEEP THEIRS (t), MERGE (b), or CANCEL (ESC)");
+        let title = format!("Conflict {} of {}", self.current + 1, self.conflicts.len());
+
+        terminal.draw(|frame| {
+            let layout = default_layout(frame);
+            render_tasks(frame, common_state, &[Modifier::DIM], layout[0]);
+            let paragraph =
+                Paragraph::new(text).block(Block::default().title(title).borders(Borders::ALL));
+            frame.render_widget(paragraph, layout[1]);
+        })?;
+        Ok(())
+    }
+
+    fn update(
+        &mut self,
+        opt: &Opt,
+        common_state: &mut CommonState,
+        key: Key,
+    ) -> Result<ActionResult> {
+        let resolution = match key {
+            key if opt.keys.keep_mine.contains(&key) => Resolution::KeepMine,
+            key if opt.keys.keep_theirs.contains(&key) => Resolution::KeepTheirs,
+            key if opt.keys.merge.contains(&key) => Resolution::Merge,
+            key if opt.keys.cancel.contains(&key) => {
+                return Ok(ActionResult {
+                    new_mode: Some(Box::new(Normal)),
+                    should_flush: false,
+                    should_load: true,
+                })
+            }
+            _ => return Ok(ActionResult::default()),
+        };
+
+        let conflict = &self.conflicts[self.current];
+        common_state
+            .resolved
+            .insert(conflict.mine.uuid.clone(), conflict.resolve(resolution)?);
+        self.current += 1;
+        if self.current < self.conflicts.len() {
+            return Ok(ActionResult::default());
+        }
+        Ok(ActionResult {
+            new_mode: Some(Box::new(Normal)),
+            should_flush: true,
+            should_load: false,
+        })
+    }
+}
+
 type Frame<'a> = tui::Frame<'a, TermionBackend<RawTerminal<Stdout>>>;
 
 fn common_render<'a>(
@@ -348,21 +615,52 @@ fn render_tasks<'a>(
     selected_modifiers: &[Modifier],
     area: Rect,
 ) {
-    let items: Vec<ListItem> = common_state
-        .tasks
+    let visible = (0..common_state.tasks.len())
+        .filter(|i| common_state.is_visible(*i))
+        .collect::<Vec<usize>>();
+    let items: Vec<ListItem> = visible
         .iter()
-        .map(|task| ListItem::new(task.description.as_str()))
+        .map(|i| {
+            let task = &common_state.tasks[*i];
+            let progress = common_state
+                .tasks_contents
+                .iter()
+                .find(|(uuid, _)| *uuid == task.uuid)
+                .and_then(|(_, contents)| checklist::progress(contents));
+            match progress {
+                Some((done, total)) => {
+                    ListItem::new(format!("{} ({}/{})", task.description, done, total))
+                }
+                None => ListItem::new(task.description.clone()),
+            }
+        })
         .collect();
 
     let mut highlight_style = Style::default();
     for modifier in selected_modifiers.iter() {
         highlight_style = highlight_style.add_modifier(*modifier);
     }
+    let mut title = if common_state.list.is_named() {
+        format!("Tasks ({})", common_state.list.name())
+    } else {
+        "Tasks".to_string()
+    };
+    if let Some(filter) = common_state.filter.as_ref() {
+        title.push_str(&format!(" /{}", filter.query));
+    }
     let list = List::new(items)
-        .block(Block::default().title("Tasks").borders(Borders::ALL))
+        .block(Block::default().title(title).borders(Borders::ALL))
         .highlight_style(highlight_style);
 
-    frame.render_stateful_widget(list, area, &mut common_state.list_state);
+    if common_state.filter.is_none() {
+        frame.render_stateful_widget(list, area, &mut common_state.list_state);
+    } else {
+        // the list only holds the tasks shown, so the selection has to be mapped onto them
+        let selected = common_state.selected();
+        let mut list_state = ListState::default();
+        list_state.select(visible.iter().position(|i| *i == selected));
+        frame.render_stateful_widget(list, area, &mut list_state);
+    }
 }
 
 fn render_contents<'a>(frame: &mut Frame<'a>, common_state: &mut CommonState, area: Rect) {
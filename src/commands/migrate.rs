@@ -0,0 +1,127 @@
+use structopt::StructOpt;
+
+use crate::commands::print_plan;
+use crate::error::Result;
+use crate::opt::Opt;
+use crate::ordering::{self, List};
+use crate::taskwarrior::{TaskBackend, Taskrc};
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// Prints the changes that would be made instead of making them.
+    #[structopt(long)]
+    pub dry_run: bool,
+}
+
+/// Moves the order stored by older versions of taskn in `estimate` over to `taskn_order`, and
+/// points any report sorted by `estimate` at `taskn_order` instead.
+pub fn execute(opt: Opt, args: Args, backend: &dyn TaskBackend) -> Result<()> {
+    let (mut tasks, migrated) = ordering::migrate(backend)?;
+    let mut taskrc = Taskrc::load(backend)?;
+    let reports = report_sorts(&taskrc);
+
+    if args.dry_run {
+        print_plan(&List::default().transaction(&mut tasks).plan(backend)?);
+        for (key, sort) in reports.iter() {
+            println!("set {}={}", key, sort);
+        }
+        return Ok(());
+    }
+
+    if migrated > 0 {
+        opt.journal().record(backend, "migrate", |backend| {
+            List::default().save(backend, &mut tasks)
+        })?;
+    }
+    for (key, sort) in reports.iter() {
+        taskrc.ensure(key, sort)?;
+        println!("set {}={}", key, sort);
+    }
+    println!("migrated {} tasks", migrated);
+    Ok(())
+}
+
+/// Finds the reports which sort by `estimate`, and returns their new sort orders.
+fn report_sorts(taskrc: &Taskrc) -> Vec<(String, String)> {
+    taskrc
+        .iter()
+        .filter(|(key, _)| key.starts_with("report.") && key.ends_with(".sort"))
+        .filter_map(|(key, sort)| {
+            let columns = sort
+                .split(',')
+                .map(|column| {
+                    // a column is an attribute followed by its direction, e.g. `estimate+` or
+                    // `due-/`, and only `estimate` itself moves over
+                    let attribute = column.trim_end_matches(&['+', '-', '/'][..]);
+                    if attribute == "estimate" {
+                        format!("{}{}", ordering::UDA, &column[attribute.len()..])
+                    } else {
+                        column.to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(",");
+            if columns == sort {
+                None
+            } else {
+                Some((key.to_string(), columns))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::{opt, task, TempDir};
+
+    #[test]
+    fn test_migrate_moves_estimates() -> Result<()> {
+        let root_dir = TempDir::new("migrate");
+        let backend = MemoryBackend::new(vec![
+            task(1).with_estimate(1),
+            task(2).with_order(0.0),
+            task(3).with_estimate(0),
+            task(4),
+        ]);
+        backend.config("report.next.sort", "estimate+,urgency-")?;
+
+        execute(opt(root_dir.path()), Args { dry_run: false }, &backend)?;
+        let ordered = List::default()
+            .load(&backend)?
+            .iter()
+            .map(|task| (task.id.unwrap(), task.estimate))
+            .collect::<Vec<(usize, Option<i32>)>>();
+        assert_eq!(ordered, vec![(4, None), (2, None), (3, None), (1, None)]);
+        assert_eq!(
+            backend.config_value("report.next.sort").as_deref(),
+            Some("taskn_order+,urgency-")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_report_sorts_only_move_estimate() -> Result<()> {
+        let backend = MemoryBackend::default();
+        backend.config("report.next.sort", "estimate+,urgency-")?;
+        backend.config("report.list.sort", "estimate-/,due+")?;
+        backend.config("report.points.sort", "estimated+,estimate_points-")?;
+
+        let reports = report_sorts(&Taskrc::load(&backend)?);
+        assert_eq!(
+            reports,
+            vec![
+                (
+                    "report.list.sort".to_string(),
+                    "taskn_order-/,due+".to_string()
+                ),
+                (
+                    "report.next.sort".to_string(),
+                    "taskn_order+,urgency-".to_string()
+                ),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,44 +1,103 @@
+pub mod completions;
 pub mod edit;
+pub mod history;
+pub mod hook;
+pub mod init;
 pub mod interactive;
+pub mod migrate;
 pub mod order;
 pub mod remind;
+pub mod search;
+pub mod undo;
 
-use std::io;
-use std::str::FromStr;
-
+use crate::error;
 use crate::opt::Opt;
+use crate::taskwarrior::{self, PlannedChange, TaskBackend};
 
-#[derive(Clone, Copy)]
+/// A command to run, along with the arguments it was given on the command line.
 pub enum Command {
-    Edit,
-    Interactive,
-    Order,
-    Remind,
+    Completions(completions::Args),
+    Edit(edit::Args),
+    History(history::Args),
+    Hook(hook::Args),
+    Init,
+    Interactive(interactive::Args),
+    Migrate(migrate::Args),
+    Order(order::Args),
+    Remind(remind::Args),
+    Search(search::Args),
+    Undo,
 }
 
 impl Command {
-    pub fn execute(self, opt: Opt) -> io::Result<()> {
+    /// The name used for this command on the command line and in the configuration file.
+    pub fn name(&self) -> &'static str {
         use Command::*;
         match self {
-            Edit => edit::execute(opt),
-            Interactive => interactive::execute(opt),
-            Order => order::execute(opt),
-            Remind => remind::execute(opt),
+            Completions(_) => "completions",
+            Edit(_) => "edit",
+            History(_) => "history",
+            Hook(_) => "hook",
+            Init => "init",
+            Interactive(_) => "interactive",
+            Migrate(_) => "migrate",
+            Order(_) => "order",
+            Remind(_) => "remind",
+            Search(_) => "search",
+            Undo => "undo",
         }
     }
-}
 
-impl FromStr for Command {
-    type Err = String;
+    /// The filter of the commands whose arguments are only a filter, which is what the `args`
+    /// from the configuration file stand in for.
+    pub fn filter_mut(&mut self) -> Option<&mut Vec<String>> {
+        use Command::*;
+        match self {
+            Edit(args) => Some(&mut args.filter),
+            Interactive(args) => Some(&mut args.filter),
+            Remind(args) => Some(&mut args.filter),
+            _ => None,
+        }
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    /// Runs the command, opening a backend first for the commands which work with tasks.
+    pub fn execute(self, opt: Opt) -> error::Result<()> {
         use Command::*;
-        match s {
-            "edit" => Ok(Edit),
-            "interactive" => Ok(Interactive),
-            "order" => Ok(Order),
-            "remind" => Ok(Remind),
-            _ => Err(format!("failed to parse Command from '{}'", s)),
+        match self {
+            Completions(args) => completions::execute(args),
+            Edit(args) => with_backend(opt, |opt, backend| edit::execute(opt, args, backend)),
+            History(args) => history::execute(opt, args),
+            // hooks run in the middle of a Taskwarrior command, so we can't ask Taskwarrior for
+            // its configuration (or anything else) while opening a backend
+            Hook(args) => hook::execute(opt, args),
+            Init => with_backend(opt, init::execute),
+            Interactive(args) => {
+                with_backend(opt, |opt, backend| interactive::execute(opt, args, backend))
+            }
+            Migrate(args) => with_backend(opt, |opt, backend| migrate::execute(opt, args, backend)),
+            Order(args) => with_backend(opt, |opt, backend| order::execute(opt, args, backend)),
+            Remind(args) => with_backend(opt, |opt, backend| remind::execute(opt, args, backend)),
+            Search(args) => with_backend(opt, |opt, backend| search::execute(opt, args, backend)),
+            Undo => with_backend(opt, undo::execute),
         }
     }
 }
+
+/// Opens the backend `opt` points at and runs `command` against it.
+fn with_backend<F>(opt: Opt, command: F) -> error::Result<()>
+where
+    F: FnOnce(Opt, &dyn TaskBackend) -> error::Result<()>,
+{
+    let backend = taskwarrior::open_backend(opt.taskrc.clone(), opt.data_location.clone())?;
+    command(opt, backend.as_ref())
+}
+
+/// Prints the changes a `--dry-run` would have made.
+fn print_plan(plan: &[PlannedChange]) {
+    if plan.is_empty() {
+        println!("no tasks would change");
+    }
+    for change in plan.iter() {
+        println!("{}", change);
+    }
+}
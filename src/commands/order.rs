@@ -1,49 +1,146 @@
-use std::cmp::Ordering;
-use std::io;
+use structopt::StructOpt;
 
+use crate::commands::print_plan;
+use crate::error::Result;
 use crate::opt::Opt;
-use crate::taskwarrior::Task;
-
-pub fn execute(opt: Opt) -> io::Result<()> {
-    let mut tasks = tasks_ordered()?;
-    if opt.args.len() > 0 {
-        // args.len() > 0 -> we want to reorder a specific task
-        assert!(opt.args.len() == 2);
-        let target_id: usize = opt.args[0].parse().unwrap();
-        let target_order: usize = opt.args[1].parse().unwrap();
-        assert!(target_order < tasks.len());
-
-        let mut target_index = None;
-        for (i, task) in tasks.iter().enumerate() {
-            if task.id == target_id {
-                target_index = Some(i);
-                break;
-            }
-        }
-        let target_index = target_index.unwrap();
+use crate::ordering::{self, Movement};
+use crate::taskwarrior::{Task, TaskBackend};
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// Prints the tasks that would be moved instead of moving them.
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// The named ordering to move the task in. Defaults to the configured list whose Taskwarrior
+    /// context is active.
+    #[structopt(long)]
+    pub list: Option<String>,
+
+    /// Sorts the tasks by a rule instead of moving one, e.g. "due,priority-,+next,project", or by
+    /// the name of a rule from the configuration file. Tasks tagged +pinned keep their positions.
+    #[structopt(long, conflicts_with = "task")]
+    pub by: Option<String>,
+
+    /// The ID, UUID or short UUID of the task to move.
+    #[structopt(requires = "movement")]
+    pub task: Option<String>,
 
-        let task = tasks.remove(target_index);
-        tasks.insert(target_order, task);
+    /// Where to move the task: a position starting from 0, `top`, `bottom`, `up [n]`, `down [n]`,
+    /// `before <task>` or `after <task>`.
+    pub movement: Vec<String>,
+}
+
+pub fn execute(opt: Opt, args: Args, backend: &dyn TaskBackend) -> Result<()> {
+    let list = opt.list(backend, args.list.as_deref(), &[])?;
+    let sort = match &args.by {
+        None => None,
+        Some(by) => Some(opt.sort_rule(by)?),
+    };
+    let mut tasks = list.load(backend)?;
+    let mut moved = None;
+    if let Some(sort) = &sort {
+        sort.sort(&mut tasks)?;
+    } else if let Some(reference) = &args.task {
+        let movement = Movement::parse(&args.movement)?;
+        moved = Some(ordering::move_task(&mut tasks, reference, &movement)?);
     }
+    print_order(&tasks, moved);
 
-    for (i, task) in tasks.iter_mut().enumerate() {
-        task.set_estimate(Some(i as i32))?;
+    if args.dry_run {
+        print_plan(&list.transaction(&mut tasks).plan(backend)?);
+        return Ok(());
+    }
+
+    let mut description = "order".to_string();
+    if list.is_named() {
+        description.push_str(&format!(" --list {}", list.name()));
+    }
+    if let Some(sort) = &sort {
+        description.push_str(&format!(" --by {}", sort));
     }
-    Ok(())
+    for arg in args.task.iter().chain(args.movement.iter()) {
+        description.push_str(&format!(" {}", arg));
+    }
+    opt.journal().record(backend, &description, |backend| {
+        list.save(backend, &mut tasks)
+    })
 }
 
-fn tasks_ordered() -> io::Result<Vec<Task>> {
-    let args = &["status:pending"];
-    let mut tasks = Task::get(args.iter())?;
-    tasks.sort_by(estimate_order);
-    Ok(tasks)
+/// Prints the tasks in order, marking the task at position `moved`.
+fn print_order(tasks: &[Task], moved: Option<usize>) {
+    for (position, task) in tasks.iter().enumerate() {
+        let marker = if Some(position) == moved { ">" } else { " " };
+        let id = match task.id {
+            Some(id) => id.to_string(),
+            None => task.uuid.chars().take(8).collect(),
+        };
+        println!("{} {:>3} {:>8} {}", marker, position, id, task.description);
+    }
 }
 
-fn estimate_order(task1: &Task, task2: &Task) -> Ordering {
-    let order = task1.estimate.partial_cmp(&task2.estimate);
-    if let Some(order) = order {
-        order
-    } else {
-        Ordering::Greater
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::error::TasknError;
+    use crate::ordering::List;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::{opt, task, TempDir};
+
+    /// Runs `taskn order` with `args` as they'd be given on the command line, journaling the run
+    /// in `root_dir`.
+    fn order(root_dir: &Path, args: &[&str], backend: &dyn TaskBackend) -> Result<()> {
+        let args = Args::from_iter_safe(std::iter::once("order").chain(args.iter().copied()))
+            .map_err(|e| TasknError::Invalid(e.to_string()))?;
+        execute(opt(root_dir), args, backend)
+    }
+
+    #[test]
+    fn test_order_moves_task() -> Result<()> {
+        let dir = TempDir::new("order");
+        let root_dir = dir.path();
+        let backend = MemoryBackend::new(vec![
+            task(1).with_order(0.0),
+            task(2).with_order(1.0),
+            task(3),
+        ]);
+        order(root_dir, &["3", "0"], &backend)?;
+        order(
+            root_dir,
+            &["1", "after", "00000000-0000-4000-8000-000000000002"],
+            &backend,
+        )?;
+
+        let ordered = List::default()
+            .load(&backend)?
+            .iter()
+            .map(|task| task.id.unwrap())
+            .collect::<Vec<usize>>();
+        assert_eq!(ordered, vec![3, 2, 1]);
+        assert_eq!(opt(root_dir).journal().entries()?.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_rejects_bad_arguments() {
+        let dir = TempDir::new("order-rejects");
+        let root_dir = dir.path();
+        let backend = MemoryBackend::new(vec![task(1).with_order(0.0), task(2).with_order(1.0)]);
+        for args in [
+            &["1"][..],
+            &["one", "0"],
+            &["1", "before", "1"],
+            &["1", "5"],
+            &["9", "0"],
+            &["1", "0", "--by", "due"],
+        ]
+        .iter()
+        {
+            assert!(order(root_dir, args, &backend).is_err());
+        }
+        // nothing was changed, so nothing was journaled either
+        assert!(!root_dir.exists());
     }
 }
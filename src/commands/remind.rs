@@ -0,0 +1,22 @@
+use structopt::StructOpt;
+
+use crate::error::Result;
+use crate::opt::Opt;
+use crate::reminders;
+use crate::taskwarrior::{Task, TaskBackend};
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// Passed along to taskwarrior while selecting tasks.
+    pub filter: Vec<String>,
+}
+
+pub fn execute(opt: Opt, args: Args, backend: &dyn TaskBackend) -> Result<()> {
+    let mut taskwarrior_args = args.filter;
+    taskwarrior_args.push("+remindme".to_string());
+    taskwarrior_args.push("(status:pending or status:waiting)".to_string());
+    let mut tasks = Task::get(backend, taskwarrior_args.into_iter())?;
+    opt.journal().record(backend, "remind", |backend| {
+        reminders::sync(backend, &mut tasks)
+    })
+}
@@ -0,0 +1,86 @@
+use std::io;
+
+use structopt::StructOpt;
+use termion::style::{Bold, Reset};
+
+use crate::commands::edit;
+use crate::error::Result;
+use crate::opt::Opt;
+use crate::search::{self, Hit, Query};
+use crate::taskwarrior::{Task, TaskBackend};
+
+#[derive(StructOpt)]
+pub struct Args {
+    /// Opens the matching notes in the editor instead of listing them.
+    #[structopt(long)]
+    pub open: bool,
+
+    /// The words to search for. Quote the query to search for several words.
+    pub query: String,
+
+    /// Passed along to taskwarrior to narrow down the tasks searched.
+    pub filter: Vec<String>,
+}
+
+pub fn execute(opt: Opt, args: Args, backend: &dyn TaskBackend) -> Result<()> {
+    let Args {
+        open,
+        query,
+        filter,
+    } = args;
+    let hits = search::search(&opt.notes(), &Query::parse(&query)?)?;
+
+    let uuids = hits
+        .iter()
+        .map(|hit| hit.uuid.as_str())
+        .collect::<Vec<&str>>();
+    let mut tasks = if filter.is_empty() {
+        Task::get_by_uuid(backend, &uuids)?
+    } else {
+        Task::get(backend, filter.iter())?
+            .into_iter()
+            .map(|task| (task.uuid.clone(), task))
+            .collect()
+    };
+    // notes whose tasks are gone, or which the filter leaves out, aren't hits
+    let hits = hits
+        .into_iter()
+        .filter_map(|hit| tasks.remove(&hit.uuid).map(|task| (hit, task)))
+        .collect::<Vec<(Hit, Task)>>();
+    if hits.is_empty() {
+        println!("no notes match '{}'", query);
+        return Ok(());
+    }
+
+    if open {
+        let tasks = hits.into_iter().map(|(_, task)| task).collect();
+        let description = std::iter::once(query)
+            .chain(filter)
+            .collect::<Vec<String>>()
+            .join(" ");
+        return edit::edit_tasks(
+            &opt,
+            &edit::Args::default(),
+            backend,
+            tasks,
+            &format!("search {}", description),
+        );
+    }
+
+    let (start, end) = if termion::is_tty(&io::stdout()) {
+        (Bold.to_string(), Reset.to_string())
+    } else {
+        (String::new(), String::new())
+    };
+    for (hit, task) in hits.iter() {
+        let id = match task.id {
+            Some(id) => id.to_string(),
+            None => task.uuid.chars().take(8).collect(),
+        };
+        println!("{} {}", id, task.description);
+        for snippet in hit.snippets.iter() {
+            println!("    {}", snippet.highlight(&start, &end));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use crate::error::{Result, TasknError};
+use crate::opt::Opt;
+use crate::taskwarrior::TaskBackend;
+
+/// Rolls back the most recent taskn operation that hasn't been undone yet.
+pub fn execute(opt: Opt, backend: &dyn TaskBackend) -> Result<()> {
+    let journal = opt.journal();
+    let entry = journal
+        .last_undoable()?
+        .ok_or_else(|| TasknError::Invalid("nothing to undo".to_string()))?;
+
+    let restored = journal.undo(backend, &entry)?;
+    println!("undid '{}' ({} tasks restored)", entry.operation, restored);
+    Ok(())
+}
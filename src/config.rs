@@ -0,0 +1,339 @@
+//! taskn's own configuration file, `~/.config/taskn/config.toml`. It provides defaults for the
+//! command line options, per-command overrides, key bindings for interactive mode, and named
+//! profiles:
+//!
+//! ```toml
+//! editor = "nvim"
+//! front_matter = true
+//! checklists = true
+//!
+//! [commands.edit]
+//! args = ["status:pending"]
+//!
+//! [keys]
+//! up = ["Up", "k"]
+//! down = ["Down", "j"]
+//!
+//! [lists.work]
+//! filter = ["project:work"]
+//!
+//! [sorts]
+//! triage = "due,priority-,+next"
+//!
+//! [profiles.work]
+//! root_dir = "~/work/taskn"
+//! taskrc = "~/work/taskrc"
+//! data_location = "~/work/task"
+//! ```
+//!
+//! Settings are picked from the command line first, then the command's section, then the
+//! selected profile, and finally the top of the file.
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use shellexpand::tilde;
+use termion::event::Key;
+
+use crate::error::{Result, TasknError};
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    pub defaults: Settings,
+    pub commands: BTreeMap<String, CommandSettings>,
+    pub keys: BTreeMap<String, KeyList>,
+    pub lists: BTreeMap<String, ListSettings>,
+    /// Sort rules for `taskn order --by`, keyed by name, see [crate::ordering::SortRule].
+    pub sorts: BTreeMap<String, String>,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+/// The options which can be set at any level of the configuration.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub editor: Option<String>,
+    pub file_format: Option<String>,
+    pub root_dir: Option<String>,
+    /// The taskrc used when running `task`, in place of `TASKRC`/`~/.taskrc`.
+    pub taskrc: Option<String>,
+    /// Overrides Taskwarrior's `data.location`.
+    pub data_location: Option<String>,
+    /// Adds front matter to notes which don't have it yet, see [crate::front_matter].
+    pub front_matter: Option<bool>,
+    /// Syncs checklists in notes with tasks, see [crate::checklist].
+    pub checklists: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct CommandSettings {
+    #[serde(flatten)]
+    pub settings: Settings,
+    /// The filter to use when none is provided on the command line, for the commands whose
+    /// arguments are only a filter.
+    pub args: Vec<String>,
+}
+
+/// A named ordering of tasks, see [crate::ordering::List]. A list which isn't configured covers
+/// every pending task.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ListSettings {
+    /// A Taskwarrior filter selecting the tasks in the list.
+    pub filter: Vec<String>,
+    /// A Taskwarrior context whose filter selects the tasks in the list. The list is picked
+    /// automatically whenever the context is active.
+    pub context: Option<String>,
+}
+
+/// Key bindings may be a single key (`done = "d"`) or a list of keys (`up = ["Up", "k"]`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Config {
+    /// Loads the configuration file at `path`. If no path is provided, this loads the default
+    /// configuration file if it exists, and otherwise returns an empty configuration.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (PathBuf::from(tilde(path).as_ref()), true),
+            None => (default_path(), false),
+        };
+
+        match fs::read_to_string(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => Ok(Config::default()),
+            Err(e) => Err(config_error(&path, e.to_string())),
+            Ok(contents) => Self::parse(&path, &contents),
+        }
+    }
+
+    fn parse(path: &Path, contents: &str) -> Result<Self> {
+        let config: Config =
+            toml::from_str(contents).map_err(|e| config_error(path, e.to_string()))?;
+        // validate key bindings up front, rather than when interactive mode starts
+        config
+            .key_bindings()
+            .map_err(|message| config_error(path, message))?;
+        Ok(config)
+    }
+
+    /// Combines `cli` with the settings for `command` and `profile`. Returns the combined
+    /// settings along with the command's default arguments.
+    pub fn resolve(
+        &self,
+        cli: Settings,
+        profile: Option<&str>,
+        command: &str,
+    ) -> Result<(Settings, Vec<String>)> {
+        let mut settings = cli;
+        let mut args = Vec::new();
+        if let Some(command_settings) = self.commands.get(command) {
+            settings = settings.or(&command_settings.settings);
+            args = command_settings.args.clone();
+        }
+        if let Some(profile) = profile {
+            let profile_settings = self
+                .profiles
+                .get(profile)
+                .ok_or_else(|| TasknError::Invalid(format!("no profile named '{}'", profile)))?;
+            settings = settings.or(profile_settings);
+        }
+        Ok((settings.or(&self.defaults), args))
+    }
+
+    /// Builds the key bindings for interactive mode, starting from [KeyBindings::default].
+    pub fn key_bindings(&self) -> std::result::Result<KeyBindings, String> {
+        let mut bindings = KeyBindings::default();
+        for (action, keys) in self.keys.iter() {
+            let keys = match keys {
+                KeyList::One(key) => vec![parse_key(key)?],
+                KeyList::Many(keys) => keys
+                    .iter()
+                    .map(|key| parse_key(key))
+                    .collect::<std::result::Result<Vec<Key>, String>>()?,
+            };
+            match action.as_str() {
+                "up" => bindings.up = keys,
+                "down" => bindings.down = keys,
+                "done" => bindings.done = keys,
+                "shift" => bindings.shift = keys,
+                "confirm" => bindings.confirm = keys,
+                "cancel" => bindings.cancel = keys,
+                "keep_mine" => bindings.keep_mine = keys,
+                "keep_theirs" => bindings.keep_theirs = keys,
+                "merge" => bindings.merge = keys,
+                "search" => bindings.search = keys,
+                _ => return Err(format!("unknown key binding '{}'", action)),
+            }
+        }
+        Ok(bindings)
+    }
+}
+
+impl Settings {
+    /// Fills in any setting that isn't set on `self` from `other`.
+    fn or(self, other: &Settings) -> Settings {
+        Settings {
+            editor: self.editor.or_else(|| other.editor.clone()),
+            file_format: self.file_format.or_else(|| other.file_format.clone()),
+            root_dir: self.root_dir.or_else(|| other.root_dir.clone()),
+            taskrc: self.taskrc.or_else(|| other.taskrc.clone()),
+            data_location: self.data_location.or_else(|| other.data_location.clone()),
+            front_matter: self.front_matter.or(other.front_matter),
+            checklists: self.checklists.or(other.checklists),
+        }
+    }
+}
+
+/// The keys bound to each action in interactive mode. Ctrl-C always quits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    pub up: Vec<Key>,
+    pub down: Vec<Key>,
+    pub done: Vec<Key>,
+    pub shift: Vec<Key>,
+    pub confirm: Vec<Key>,
+    pub cancel: Vec<Key>,
+    pub keep_mine: Vec<Key>,
+    pub keep_theirs: Vec<Key>,
+    pub merge: Vec<Key>,
+    pub search: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: vec![Key::Up],
+            down: vec![Key::Down],
+            done: vec![Key::Char('d')],
+            shift: vec![Key::Char('s')],
+            confirm: vec![Key::Char('\n')],
+            cancel: vec![Key::Esc, Key::Ctrl('f')],
+            keep_mine: vec![Key::Char('m')],
+            keep_theirs: vec![Key::Char('t')],
+            merge: vec![Key::Char('b')],
+            search: vec![Key::Char('/')],
+        }
+    }
+}
+
+/// Parses a key such as `k`, `Enter`, `Esc`, or `Ctrl-f`.
+fn parse_key(s: &str) -> std::result::Result<Key, String> {
+    let single_char = |s: &str| {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    let lower = s.to_lowercase();
+    let key = match lower.as_str() {
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "enter" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "esc" => Key::Esc,
+        "backspace" => Key::Backspace,
+        _ => {
+            if let Some(c) = lower.strip_prefix("ctrl-").and_then(single_char) {
+                Key::Ctrl(c)
+            } else if let Some(c) = lower.strip_prefix("alt-").and_then(single_char) {
+                Key::Alt(c)
+            } else if let Some(c) = single_char(s) {
+                Key::Char(c)
+            } else {
+                return Err(format!("unknown key '{}'", s));
+            }
+        }
+    };
+    Ok(key)
+}
+
+fn default_path() -> PathBuf {
+    let config_home = match env::var("XDG_CONFIG_HOME") {
+        Ok(config_home) if !config_home.is_empty() => PathBuf::from(config_home),
+        _ => PathBuf::from(tilde("~/.config").as_ref()),
+    };
+    config_home.join("taskn").join("config.toml")
+}
+
+fn config_error(path: &Path, message: String) -> TasknError {
+    TasknError::Config {
+        path: path.to_path_buf(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        editor = "nvim"
+        root_dir = "~/taskn"
+
+        [commands.edit]
+        file_format = "txt"
+        args = ["status:pending"]
+
+        [keys]
+        up = ["Up", "k"]
+        done = "x"
+
+        [profiles.work]
+        root_dir = "~/work/taskn"
+        taskrc = "~/work/taskrc"
+    "#;
+
+    #[test]
+    fn test_resolve_precedence() -> Result<()> {
+        let config = Config::parse(Path::new("config.toml"), CONFIG)?;
+        let cli = Settings {
+            editor: Some("vi".to_string()),
+            ..Settings::default()
+        };
+
+        let (settings, args) = config.resolve(cli, Some("work"), "edit")?;
+        assert_eq!(settings.editor.as_deref(), Some("vi"));
+        assert_eq!(settings.file_format.as_deref(), Some("txt"));
+        assert_eq!(settings.root_dir.as_deref(), Some("~/work/taskn"));
+        assert_eq!(settings.taskrc.as_deref(), Some("~/work/taskrc"));
+        assert_eq!(args, vec!["status:pending".to_string()]);
+
+        let (settings, args) = config.resolve(Settings::default(), None, "interactive")?;
+        assert_eq!(settings.root_dir.as_deref(), Some("~/taskn"));
+        assert!(args.is_empty());
+
+        assert!(config
+            .resolve(Settings::default(), Some("home"), "edit")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_bindings() -> Result<()> {
+        let bindings = Config::parse(Path::new("config.toml"), CONFIG)?
+            .key_bindings()
+            .unwrap();
+        assert_eq!(bindings.up, vec![Key::Up, Key::Char('k')]);
+        assert_eq!(bindings.done, vec![Key::Char('x')]);
+        assert_eq!(bindings.cancel, KeyBindings::default().cancel);
+
+        assert!(Config::parse(Path::new("config.toml"), "[keys]\nfly = \"f\"").is_err());
+        assert!(Config::parse(Path::new("config.toml"), "[keys]\nup = \"Hyper-k\"").is_err());
+        Ok(())
+    }
+}
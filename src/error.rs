@@ -0,0 +1,115 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::result;
+
+#[derive(Debug)]
+pub enum TasknError {
+    /// Used when a process (`task` or the editor) could not be started at all.
+    Spawn { program: String, source: io::Error },
+
+    /// Used when a process ran but exited unsuccessfully. `stderr` holds whatever the process
+    /// printed to stderr, which for Taskwarrior is usually the reason it failed.
+    Exit {
+        program: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+
+    /// Used when Taskwarrior's output could not be decoded. `context` describes what we were
+    /// trying to decode.
+    Decode {
+        context: String,
+        source: serde_json::Error,
+    },
+
+    /// Used when Taskwarrior's data could not be read directly from disk.
+    Store { path: PathBuf, message: String },
+
+    /// Used when taskn's configuration file can't be read or is invalid.
+    Config { path: PathBuf, message: String },
+
+    /// Used when reading or writing a note fails.
+    Note { path: PathBuf, source: io::Error },
+
+    /// Used when reading or writing the journal of changes that `taskn undo` reverts fails.
+    Journal { path: PathBuf, source: io::Error },
+
+    /// Used when the user's input (arguments, filters, ...) is invalid.
+    Invalid(String),
+
+    /// Used when syncing reminders with the operating system fails.
+    Reminder(String),
+
+    /// Used when writing a [Transaction](crate::taskwarrior::Transaction) fails. `rollback` holds
+    /// the error from restoring the tasks afterwards, if that failed too.
+    Transaction {
+        source: Box<TasknError>,
+        rollback: Option<Box<TasknError>>,
+    },
+
+    /// General case for any other IO error.
+    Io(io::Error),
+}
+
+pub type Result<T> = result::Result<T, TasknError>;
+
+impl fmt::Display for TasknError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TasknError::*;
+        match self {
+            Spawn { program, source } => write!(f, "failed to run '{}': {}", program, source),
+            Exit {
+                program,
+                status,
+                stderr,
+            } => {
+                write!(f, "'{}' failed ({})", program, status)?;
+                if !stderr.is_empty() {
+                    write!(f, ": {}", stderr)?;
+                }
+                Ok(())
+            }
+            Decode { context, source } => write!(f, "failed to decode {}: {}", context, source),
+            Store { path, message } => {
+                write!(f, "failed to read '{}': {}", path.display(), message)
+            }
+            Config { path, message } => write!(f, "config '{}': {}", path.display(), message),
+            Note { path, source } => write!(f, "note '{}': {}", path.display(), source),
+            Journal { path, source } => write!(f, "journal '{}': {}", path.display(), source),
+            Invalid(message) => write!(f, "{}", message),
+            Reminder(message) => write!(f, "failed to sync reminders: {}", message),
+            Transaction { source, rollback } => match rollback {
+                None => write!(f, "{} (the changes were rolled back)", source),
+                Some(rollback) => write!(
+                    f,
+                    "{} (and restoring the tasks failed, some changes may remain: {})",
+                    source, rollback
+                ),
+            },
+            Io(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+impl error::Error for TasknError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        use TasknError::*;
+        match self {
+            Spawn { source, .. } | Note { source, .. } | Journal { source, .. } | Io(source) => {
+                Some(source)
+            }
+            Decode { source, .. } => Some(source),
+            Transaction { source, .. } => Some(source.as_ref()),
+            Exit { .. } | Store { .. } | Config { .. } | Invalid(_) | Reminder(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for TasknError {
+    fn from(e: io::Error) -> Self {
+        TasknError::Io(e)
+    }
+}
@@ -0,0 +1,214 @@
+//! Front matter mirrors a task's attributes at the top of its note, as a TOML block between `+++`
+//! lines:
+//!
+//! ```text
+//! +++
+//! description = "write the report"
+//! project = "work"
+//! tags = ["next"]
+//! due = "2021-03-01"
+//! priority = "H"
+//! +++
+//! the rest of the note
+//! ```
+//!
+//! taskn refreshes the block from Taskwarrior before opening a note, and pushes any edits to it
+//! back to Taskwarrior once the editor exits. Removing a line clears that attribute.
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{ParsableDateTime, Task};
+
+const DELIMITER: &str = "+++";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrontMatter {
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// The task's tags, apart from `taskn`, which taskn manages itself.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The due date in local time, written as a date when it's at midnight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+}
+
+impl FrontMatter {
+    pub fn from_task(task: &Task) -> Self {
+        FrontMatter {
+            description: task.description.clone(),
+            project: task.project.clone(),
+            tags: task
+                .tags
+                .iter()
+                .flatten()
+                .filter(|tag| *tag != "taskn")
+                .cloned()
+                .collect(),
+            due: task.due.as_ref().map(|due| {
+                let due = due.0.naive_local();
+                if due.time() == NaiveTime::from_hms(0, 0, 0) {
+                    due.format(DATE_FORMAT).to_string()
+                } else {
+                    due.format(DATE_TIME_FORMAT).to_string()
+                }
+            }),
+            priority: task.priority.clone(),
+        }
+    }
+
+    /// Parses the front matter at the top of `contents`. Returns `None` when the note doesn't
+    /// start with front matter.
+    pub fn parse(contents: &str) -> Result<Option<Self>> {
+        let block = match split(contents) {
+            None => return Ok(None),
+            Some((block, _)) => block,
+        };
+        let front_matter: FrontMatter = toml::from_str(block)
+            .map_err(|e| TasknError::Invalid(format!("invalid front matter: {}", e)))?;
+        if front_matter.description.trim().is_empty() {
+            return Err(TasknError::Invalid(
+                "invalid front matter: the description can't be empty".to_string(),
+            ));
+        }
+        Ok(Some(front_matter))
+    }
+
+    /// Writes the front matter above `body`.
+    pub fn render(&self, body: &str) -> Result<String> {
+        let block = toml::to_string(self)
+            .map_err(|e| TasknError::Invalid(format!("can't write front matter: {}", e)))?;
+        Ok(format!("{}\n{}{}\n{}", DELIMITER, block, DELIMITER, body))
+    }
+
+    /// Copies the front matter onto `task`. Returns whether the task changed.
+    pub fn apply(&self, task: &mut Task) -> Result<bool> {
+        let current = FrontMatter::from_task(task);
+        if *self == current {
+            return Ok(false);
+        }
+
+        task.description = self.description.clone();
+        task.project = self.project.clone();
+        task.priority = self.priority.clone();
+        if self.tags != current.tags {
+            let mut tags = self.tags.clone();
+            if task.has_tag("taskn") {
+                tags.push("taskn".to_string());
+            }
+            task.tags = if tags.is_empty() { None } else { Some(tags) };
+        }
+        // only reparse the due date when it was edited, so that seconds aren't lost
+        if self.due != current.due {
+            task.due = self.due.as_deref().map(parse_due).transpose()?;
+        }
+        Ok(true)
+    }
+}
+
+/// Splits `contents` into its front matter block (without the delimiters) and the rest of the
+/// note. Returns `None` when the note doesn't start with front matter.
+pub fn split(contents: &str) -> Option<(&str, &str)> {
+    let rest = contents
+        .strip_prefix(DELIMITER)?
+        .trim_start_matches([' ', '\t'].as_ref());
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == DELIMITER {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The part of `contents` after its front matter, or all of it if it has none.
+pub fn body(contents: &str) -> &str {
+    match split(contents) {
+        Some((_, body)) => body,
+        None => contents,
+    }
+}
+
+fn parse_due(due: &str) -> Result<ParsableDateTime> {
+    let local = NaiveDateTime::parse_from_str(due, DATE_TIME_FORMAT)
+        .or_else(|_| NaiveDate::parse_from_str(due, DATE_FORMAT).map(|date| date.and_hms(0, 0, 0)))
+        .ok()
+        .and_then(|due| Local.from_local_datetime(&due).earliest());
+    match local {
+        Some(due) => Ok(ParsableDateTime::from(due)),
+        None => due.parse().map_err(|_| {
+            TasknError::Invalid(format!(
+                "invalid due date '{}' in front matter, expected e.g. 2021-03-01 or 2021-03-01T17:00:00",
+                due
+            ))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_round_trip_and_apply() -> Result<()> {
+        let mut task = Task {
+            project: Some("work".to_string()),
+            due: Some(ParsableDateTime::from(
+                Local
+                    .from_local_datetime(&NaiveDate::from_ymd(2021, 3, 1).and_hms(0, 0, 0))
+                    .unwrap(),
+            )),
+            ..test_support::task(1)
+                .with_description("write the report")
+                .with_tags(&["next", "taskn"])
+        };
+        let note = FrontMatter::from_task(&task).render("some notes\n")?;
+        assert_eq!(
+            note,
+            "+++\ndescription = \"write the report\"\nproject = \"work\"\ntags = [\"next\"]\ndue = \"2021-03-01\"\n+++\nsome notes\n"
+        );
+        assert_eq!(body(&note), "some notes\n");
+        assert!(!FrontMatter::parse(&note)?.unwrap().apply(&mut task)?);
+
+        let edited = "+++\ndescription = \"write the report\"\ntags = [\"next\", \"review\"]\ndue = \"2021-03-02T17:30:00\"\npriority = \"H\"\n+++\n";
+        assert!(FrontMatter::parse(edited)?.unwrap().apply(&mut task)?);
+        assert_eq!(task.project, None);
+        assert_eq!(task.priority.as_deref(), Some("H"));
+        assert_eq!(
+            task.tags,
+            Some(vec![
+                "next".to_string(),
+                "review".to_string(),
+                "taskn".to_string()
+            ])
+        );
+        assert_eq!(
+            FrontMatter::from_task(&task).due.as_deref(),
+            Some("2021-03-02T17:30:00")
+        );
+
+        assert!(FrontMatter::parse("no front matter\n+++\n")?.is_none());
+        assert!(FrontMatter::parse("+++\ndescription = \"\"\n+++\n").is_err());
+        assert!(
+            FrontMatter::parse("+++\ndue = \"soon\"\ndescription = \"a\"\n+++\n")?
+                .unwrap()
+                .apply(&mut task)
+                .is_err()
+        );
+        Ok(())
+    }
+}
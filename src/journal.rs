@@ -0,0 +1,418 @@
+//! A record of every change taskn makes to Taskwarrior, so that whole taskn operations (e.g. an
+//! entire reorder) can be rolled back in one step. Each operation is one JSON line in
+//! `<root_dir>/.journal.jsonl`, holding a snapshot of every task it touched from before and after
+//! the operation.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{ParsableDateTime, Task, TaskBackend, Transaction};
+
+const JOURNAL_NAME: &str = ".journal.jsonl";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    pub id: u64,
+    pub time: ParsableDateTime,
+    /// A description of the operation, e.g. `order 3 0`.
+    pub operation: String,
+    pub changes: Vec<Change>,
+    /// Set on the entries written by `taskn undo`, to the ID of the entry that was undone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverts: Option<u64>,
+}
+
+/// A single task's state from before and after an operation. `before` is `None` if the operation
+/// created the task.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Change {
+    pub uuid: String,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new<P: Into<PathBuf>>(root_dir: P) -> Self {
+        Self {
+            path: root_dir.into().join(JOURNAL_NAME),
+        }
+    }
+
+    /// Runs `operation` against a backend which records every task it changes, and then writes
+    /// those changes to the journal. Changes are written even if `operation` fails partway
+    /// through, so that whatever it did can still be undone.
+    pub fn record<T, F>(
+        &self,
+        backend: &dyn TaskBackend,
+        description: &str,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&dyn TaskBackend) -> Result<T>,
+    {
+        self.record_entry(backend, description, None, operation)
+    }
+
+    /// Returns every entry in the journal, oldest first. Lines which can't be read as an entry
+    /// (e.g. ones written by another version of taskn) are skipped.
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.journal_error(e)),
+            Ok(contents) => contents,
+        };
+
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Returns the most recent entry which hasn't been undone yet, skipping the entries written by
+    /// undo itself.
+    pub fn last_undoable(&self) -> Result<Option<Entry>> {
+        let entries = self.entries()?;
+        let reverted = entries
+            .iter()
+            .filter_map(|entry| entry.reverts)
+            .collect::<Vec<u64>>();
+        Ok(entries
+            .into_iter()
+            .rev()
+            .find(|entry| entry.reverts.is_none() && !reverted.contains(&entry.id)))
+    }
+
+    /// Rolls back every change in `entry`. Fails without changing anything if any of the tasks
+    /// have been changed since `entry`. The rollback is always recorded, even when there turns out
+    /// to be nothing to restore, so that `entry` counts as undone either way. Returns how many
+    /// tasks were restored.
+    pub fn undo(&self, backend: &dyn TaskBackend, entry: &Entry) -> Result<usize> {
+        let mut restored = Vec::new();
+        for change in entry.changes.iter() {
+            let current = backend
+                .export(std::slice::from_ref(&change.uuid))?
+                .into_iter()
+                .find(|task| task.uuid == change.uuid);
+            let after_modified = change
+                .after
+                .as_ref()
+                .and_then(|task| task.modified.as_ref());
+            if current.as_ref().and_then(|task| task.modified.as_ref()) != after_modified {
+                return Err(TasknError::Invalid(format!(
+                    "can't undo '{}': task {} has changed since",
+                    entry.operation, change.uuid
+                )));
+            }
+
+            match (&change.before, current) {
+                (Some(before), _) => restored.push(before.clone()),
+                // the operation created this task, so undoing it deletes the task
+                (None, Some(mut current)) => {
+                    current.status = "deleted".to_string();
+                    restored.push(current);
+                }
+                (None, None) => {}
+            }
+        }
+
+        let description = format!("undo {}", entry.operation);
+        let plan = self.record_entry(backend, &description, Some(entry.id), |backend| {
+            restored
+                .into_iter()
+                .collect::<Transaction>()
+                .commit(backend)
+        })?;
+        Ok(plan.len())
+    }
+
+    fn record_entry<T, F>(
+        &self,
+        backend: &dyn TaskBackend,
+        description: &str,
+        reverts: Option<u64>,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(&dyn TaskBackend) -> Result<T>,
+    {
+        let recording = RecordingBackend::new(backend);
+        let result = operation(&recording);
+
+        let changes = recording.into_changes();
+        if !changes.is_empty() || reverts.is_some() {
+            let entry = Entry {
+                id: self.next_id(),
+                time: ParsableDateTime::from(Local::now()),
+                operation: description.to_string(),
+                changes,
+                reverts,
+            };
+            // the tasks have already been changed by now, so failing to record that mustn't turn
+            // into an error for the operation itself
+            if let Err(e) = self.append(&entry) {
+                eprintln!(
+                    "taskn: couldn't record '{}' in the journal: {}",
+                    description, e
+                );
+            }
+        }
+        result
+    }
+
+    /// The ID for the next entry, one more than the last entry's. Only the last line with an ID
+    /// is decoded, so lines that can't be read don't get in the way.
+    fn next_id(&self) -> u64 {
+        #[derive(Deserialize)]
+        struct Id {
+            id: u64,
+        }
+
+        let contents = fs::read_to_string(&self.path).unwrap_or_default();
+        contents
+            .lines()
+            .rev()
+            .find_map(|line| serde_json::from_str::<Id>(line).ok())
+            .map_or(1, |last| last.id + 1)
+    }
+
+    fn append(&self, entry: &Entry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|e| self.journal_error(e))?;
+        }
+        let line = serde_json::to_string(entry).map_err(|source| TasknError::Decode {
+            context: format!("journal entry '{}'", entry.operation),
+            source,
+        })?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.journal_error(e))?;
+        writeln!(file, "{}", line).map_err(|e| self.journal_error(e))
+    }
+
+    fn journal_error(&self, source: io::Error) -> TasknError {
+        TasknError::Journal {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+/// Wraps another backend, snapshotting each task before and after it's changed.
+struct RecordingBackend<'a> {
+    inner: &'a dyn TaskBackend,
+    changes: RefCell<BTreeMap<String, Change>>,
+    order: RefCell<Vec<String>>,
+}
+
+impl<'a> RecordingBackend<'a> {
+    fn new(inner: &'a dyn TaskBackend) -> Self {
+        Self {
+            inner,
+            changes: RefCell::new(BTreeMap::new()),
+            order: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Records the state of the tasks before they're changed. Tasks which were already changed
+    /// during this operation keep their original snapshot.
+    fn before(&self, uuids: &[&str]) -> Result<()> {
+        let unseen = uuids
+            .iter()
+            .filter(|uuid| !self.changes.borrow().contains_key(**uuid))
+            .copied()
+            .collect::<Vec<&str>>();
+        let mut snapshot = Task::get_by_uuid(self.inner, &unseen)?;
+
+        let mut changes = self.changes.borrow_mut();
+        for uuid in unseen.into_iter() {
+            self.order.borrow_mut().push(uuid.to_string());
+            changes.insert(
+                uuid.to_string(),
+                Change {
+                    uuid: uuid.to_string(),
+                    before: snapshot.remove(uuid),
+                    after: None,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn after(&self, uuids: &[&str]) -> Result<()> {
+        let mut snapshot = Task::get_by_uuid(self.inner, uuids)?;
+        let mut changes = self.changes.borrow_mut();
+        for uuid in uuids.iter() {
+            if let Some(change) = changes.get_mut(*uuid) {
+                change.after = snapshot.remove(*uuid);
+            }
+        }
+        Ok(())
+    }
+
+    fn into_changes(self) -> Vec<Change> {
+        let mut changes = self.changes.into_inner();
+        self.order
+            .into_inner()
+            .iter()
+            .filter_map(|uuid| changes.remove(uuid))
+            .collect()
+    }
+}
+
+impl<'a> TaskBackend for RecordingBackend<'a> {
+    fn export(&self, filter: &[String]) -> Result<Vec<Task>> {
+        self.inner.export(filter)
+    }
+
+    fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()> {
+        self.before(&[uuid])?;
+        let result = self.inner.modify(uuid, modifications);
+        self.after(&[uuid])?;
+        result
+    }
+
+    fn import(&self, tasks: &[Task]) -> Result<()> {
+        let uuids = tasks
+            .iter()
+            .map(|task| task.uuid.as_str())
+            .collect::<Vec<&str>>();
+        self.before(&uuids)?;
+        let result = self.inner.import(tasks);
+        self.after(&uuids)?;
+        result
+    }
+
+    fn add(&self, description: &str, modifications: &[String]) -> Result<String> {
+        let uuid = self.inner.add(description, modifications)?;
+        self.order.borrow_mut().push(uuid.clone());
+        self.changes.borrow_mut().insert(
+            uuid.clone(),
+            Change {
+                uuid: uuid.clone(),
+                before: None,
+                after: None,
+            },
+        );
+        self.after(&[&uuid])?;
+        Ok(uuid)
+    }
+
+    fn config(&self, key: &str, value: &str) -> Result<()> {
+        self.inner.config(key, value)
+    }
+
+    fn show(&self) -> Result<BTreeMap<String, String>> {
+        self.inner.show()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::{task, TempDir};
+
+    #[test]
+    fn test_undo_reverts_whole_operation() -> Result<()> {
+        let root_dir = TempDir::new("journal");
+        let journal = Journal::new(root_dir.path());
+        let backend = MemoryBackend::new(vec![
+            task(1).with_estimate(0),
+            task(2).with_estimate(1),
+            task(3).with_estimate(2),
+        ]);
+
+        journal.record(&backend, "order 3 0", |backend| {
+            backend.import(&[
+                task(3).with_estimate(0),
+                task(1).with_estimate(1),
+                task(2).with_estimate(2),
+            ])
+        })?;
+        journal.record(&backend, "edit 1", |backend| {
+            backend.modify(&task(1).uuid, &["+taskn".to_string()])
+        })?;
+        let estimates = |backend: &MemoryBackend| {
+            backend
+                .tasks()
+                .iter()
+                .map(|task| task.estimate.unwrap())
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(estimates(&backend), vec![1, 2, 0]);
+
+        let entry = journal.last_undoable()?.unwrap();
+        assert_eq!(entry.operation, "edit 1");
+        journal.undo(&backend, &entry)?;
+        assert!(!backend.tasks()[0].has_tag("taskn"));
+
+        let entry = journal.last_undoable()?.unwrap();
+        assert_eq!(entry.changes.len(), 3);
+        assert_eq!(journal.undo(&backend, &entry)?, 3);
+        assert_eq!(estimates(&backend), vec![0, 1, 2]);
+
+        assert!(journal.last_undoable()?.is_none());
+        assert_eq!(journal.entries()?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_edge_cases() -> Result<()> {
+        let root_dir = TempDir::create("journal-undo")?;
+        let journal = Journal::new(root_dir.path());
+        let modified = |day: u32| {
+            Some(ParsableDateTime::from(Local.from_utc_datetime(
+                &NaiveDate::from_ymd(2021, 3, day).and_hms(0, 0, 0),
+            )))
+        };
+        let mut first = task(1).with_estimate(0);
+        first.modified = modified(1);
+        let backend = MemoryBackend::new(vec![first.clone(), task(2).with_estimate(0)]);
+
+        // lines which can't be decoded don't stop anything from being recorded
+        fs::write(
+            root_dir.path().join(JOURNAL_NAME),
+            "{\"id\": 7}\nnot json\n",
+        )?;
+
+        let mut moved = first.clone();
+        moved.estimate = Some(5);
+        moved.modified = modified(2);
+        journal.record(&backend, "order 1 5", |backend| backend.import(&[moved]))?;
+        let entry = journal.last_undoable()?.unwrap();
+        assert_eq!(entry.id, 8);
+
+        // the task was changed again after the entry, so undoing it would lose that change
+        let mut edited = backend.tasks()[0].clone();
+        edited.description = "edited elsewhere".to_string();
+        edited.modified = modified(3);
+        backend.import(&[edited])?;
+        assert!(journal.undo(&backend, &entry).is_err());
+        assert_eq!(backend.tasks()[0].description, "edited elsewhere");
+        assert_eq!(journal.last_undoable()?.unwrap().id, 8);
+
+        // undoing an operation which didn't end up changing anything still counts as undoing it
+        journal.record(&backend, "order 2 0", |backend| {
+            backend.import(&[task(2).with_estimate(0)])
+        })?;
+        let entry = journal.last_undoable()?.unwrap();
+        assert_eq!(entry.operation, "order 2 0");
+        assert_eq!(journal.undo(&backend, &entry)?, 0);
+        assert_eq!(journal.last_undoable()?.unwrap().id, 8);
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+//! taskn associates notes with [Taskwarrior](https://taskwarrior.org/) tasks. Besides the `taskn`
+//! binary, this crate can be used to build other tools on the same task and note logic:
+//!
+//! - [taskwarrior] models tasks ([Task]) and talks to Taskwarrior through a [TaskBackend].
+//! - [notes] finds, reads, and tags the notes associated with tasks.
+//! - [templates] renders the templates new notes start from.
+//! - [front_matter] mirrors a task's attributes at the top of its note.
+//! - [checklist] turns checklists in notes into tasks.
+//! - [ordering] reads and writes taskn's ordering of pending tasks.
+//! - [search] searches the text of notes.
+//! - [reminders] syncs tasks to the operating system's reminders.
+//! - [journal] records the changes made to tasks, so that they can be undone.
+//!
+//! ```no_run
+//! use taskn::notes::NoteStore;
+//! use taskn::taskwarrior::{self, Task};
+//!
+//! # fn main() -> taskn::Result<()> {
+//! let backend = taskwarrior::open_backend(None, None)?;
+//! let notes = NoteStore::new("/home/me/.taskn", "md");
+//! for task in Task::get(backend.as_ref(), ["+taskn"].iter())? {
+//!     println!("{}: {}", task.description, notes.load(&task)?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+pub mod checklist;
+pub mod config;
+pub mod error;
+pub mod front_matter;
+pub mod journal;
+pub mod notes;
+pub mod ordering;
+pub mod reminders;
+pub mod search;
+pub mod taskwarrior;
+pub mod templates;
+
+// the command line interface, which backs the `taskn` binary
+#[doc(hidden)]
+pub mod commands;
+#[doc(hidden)]
+pub mod opt;
+
+#[cfg(test)]
+mod test_support;
+
+pub use error::{Result, TasknError};
+pub use notes::NoteStore;
+pub use taskwarrior::{Task, TaskBackend};
@@ -1,12 +1,16 @@
-mod commands;
-mod opt;
-mod taskwarrior;
+use std::process::exit;
 
-use std::io;
+use taskn::opt::Opt;
+use taskn::Result;
 
-use opt::Opt;
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("taskn: {}", e);
+        exit(1)
+    }
+}
 
-fn main() -> io::Result<()> {
-    let opt = Opt::from_args();
-    opt.command.execute(opt)
+fn run() -> Result<()> {
+    let (opt, command) = Opt::from_args()?;
+    command.execute(opt)
 }
@@ -0,0 +1,282 @@
+//! Where taskn keeps notes. Every task's note lives at `<root_dir>/<uuid>.<file_format>`, and
+//! notes of deleted tasks are moved into `<root_dir>/.trash/`. New notes are started from the
+//! [Templates] in `<root_dir>/templates/`, and may start with [FrontMatter] mirroring the task.
+//! Which template a note was started from is kept in `<root_dir>/.templated/<uuid>.json`.
+use std::fs::{self, create_dir_all, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, TasknError};
+use crate::front_matter::{self, FrontMatter};
+use crate::taskwarrior::Task;
+use crate::templates::{self, Templates, TEMPLATES_DIR};
+
+const TRASH_DIR: &str = ".trash";
+const TEMPLATED_DIR: &str = ".templated";
+
+/// The template a note was started from, and the [templates::fingerprint] of what it rendered.
+#[derive(Deserialize, Serialize)]
+struct Templated {
+    template: String,
+    fingerprint: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct NoteStore {
+    root_dir: PathBuf,
+    file_format: String,
+}
+
+impl NoteStore {
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(root_dir: P, file_format: S) -> Self {
+        Self {
+            root_dir: root_dir.into(),
+            file_format: file_format.into(),
+        }
+    }
+
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    pub fn file_format(&self) -> &str {
+        &self.file_format
+    }
+
+    /// The templates new notes are started from.
+    pub fn templates(&self) -> Templates {
+        Templates::new(self.root_dir.join(TEMPLATES_DIR), &self.file_format)
+    }
+
+    /// Creates the root directory if it doesn't already exist.
+    pub fn create_root_dir(&self) -> Result<()> {
+        create_dir_all(&self.root_dir).map_err(|source| TasknError::Note {
+            path: self.root_dir.clone(),
+            source,
+        })
+    }
+
+    /// The path of the note for `task`, whether or not it exists.
+    pub fn path(&self, task: &Task) -> PathBuf {
+        self.root_dir
+            .join(&task.uuid)
+            .with_extension(&self.file_format)
+    }
+
+    /// Lists the notes in the root directory, as the UUID of each note's task and its path.
+    pub fn entries(&self) -> Result<Vec<(String, PathBuf)>> {
+        let note_error = |source| TasknError::Note {
+            path: self.root_dir.clone(),
+            source,
+        };
+        let entries = match fs::read_dir(&self.root_dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(note_error(e)),
+            Ok(entries) => entries,
+        };
+
+        let mut notes = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(note_error)?.path();
+            // skip hidden files, like the search index
+            let hidden = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.starts_with('.'),
+                None => true,
+            };
+            if hidden
+                || !path.is_file()
+                || path.extension().and_then(|extension| extension.to_str())
+                    != Some(self.file_format.as_str())
+            {
+                continue;
+            }
+            if let Some(uuid) = path.file_stem().and_then(|stem| stem.to_str()) {
+                notes.push((uuid.to_string(), path.clone()));
+            }
+        }
+        notes.sort();
+        Ok(notes)
+    }
+
+    /// The path the note for `task` is moved to when the task is deleted.
+    pub fn trash_path(&self, task: &Task) -> PathBuf {
+        self.root_dir
+            .join(TRASH_DIR)
+            .join(&task.uuid)
+            .with_extension(&self.file_format)
+    }
+
+    /// Determines whether `task` has a note with anything other than whitespace in it. Front
+    /// matter doesn't count, and neither does a note which is still exactly what its template
+    /// rendered to when the note was started.
+    pub fn has_note(&self, task: &Task) -> Result<bool> {
+        // a lot of editors will keep an "empty" line at the top of a file, so a naive 'byte size
+        // == 0' check won't cut it.
+        //
+        // because we expect notes to be VERY small (on the order of KB at most), we can just scan
+        // to see if there's any non-whitespace.
+        //
+        // NOTE: if perf becomes an issue, this will become a good place to refactor
+        let contents = self.load(task)?;
+        if front_matter::body(&contents)
+            .chars()
+            .all(char::is_whitespace)
+        {
+            return Ok(false);
+        }
+
+        match self.templated(task)? {
+            Some(templated) => Ok(templated.fingerprint != templates::fingerprint(&contents)),
+            None => Ok(true),
+        }
+    }
+
+    /// Starts the note for `task` from its template, unless the note already exists. `template`
+    /// is the template picked on the command line, if any. Returns whether a note was written.
+    pub fn create(&self, task: &Task, template: Option<&str>) -> Result<bool> {
+        let path = self.path(task);
+        if path.exists() {
+            return Ok(false);
+        }
+        let (template, contents) =
+            match self
+                .templates()
+                .render_for(task, template, Local::now().naive_local().date())?
+            {
+                None => return Ok(false),
+                Some(rendered) => rendered,
+            };
+
+        let note_error = |source| TasknError::Note {
+            path: path.clone(),
+            source,
+        };
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(note_error)?;
+
+        let templated = Templated {
+            fingerprint: templates::fingerprint(&contents),
+            template,
+        };
+        let record = self.templated_path(task);
+        let json = serde_json::to_string(&templated).map_err(|source| TasknError::Decode {
+            context: format!("the template record {}", record.display()),
+            source,
+        })?;
+        create_dir_all(self.root_dir.join(TEMPLATED_DIR))
+            .and_then(|_| fs::write(&record, json))
+            .map_err(|source| TasknError::Note {
+                path: record,
+                source,
+            })?;
+        Ok(true)
+    }
+
+    fn templated_path(&self, task: &Task) -> PathBuf {
+        self.root_dir
+            .join(TEMPLATED_DIR)
+            .join(&task.uuid)
+            .with_extension("json")
+    }
+
+    /// Reads which template `task`'s note was started from, if it was. A record which can't be
+    /// read counts as none, i.e. the note is treated as written in.
+    fn templated(&self, task: &Task) -> Result<Option<Templated>> {
+        let path = self.templated_path(task);
+        match fs::read_to_string(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(TasknError::Note { path, source }),
+            Ok(json) => Ok(serde_json::from_str(&json).ok()),
+        }
+    }
+
+    /// Rewrites the front matter of `task`'s note from the task. Notes without front matter are
+    /// left alone unless `add` is set.
+    pub fn refresh_front_matter(&self, task: &Task, add: bool) -> Result<()> {
+        let contents = self.load(task)?;
+        let body = match front_matter::split(&contents) {
+            Some((_, body)) => body,
+            None if add => &contents,
+            None => return Ok(()),
+        };
+        let refreshed = FrontMatter::from_task(task).render(body)?;
+        // leave the note untouched when nothing changed, so that its modification time still
+        // says when it was last edited
+        if refreshed != contents {
+            self.save(task, &refreshed)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the front matter of `task`'s note, if it has any.
+    pub fn front_matter(&self, task: &Task) -> Result<Option<FrontMatter>> {
+        FrontMatter::parse(&self.load(task)?).map_err(|e| {
+            TasknError::Invalid(format!("note '{}': {}", self.path(task).display(), e))
+        })
+    }
+
+    /// Loads the contents of the note for `task`, which is empty if there is no note.
+    pub fn load(&self, task: &Task) -> Result<String> {
+        let path = self.path(task);
+        let note_error = |source| TasknError::Note {
+            path: path.clone(),
+            source,
+        };
+        match File::open(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok("".to_string()),
+            Err(e) => Err(note_error(e)),
+            Ok(mut file) => {
+                let mut buffer = String::new();
+                file.read_to_string(&mut buffer).map_err(note_error)?;
+                Ok(buffer)
+            }
+        }
+    }
+
+    /// Replaces the contents of the note for `task`.
+    pub fn save(&self, task: &Task, contents: &str) -> Result<()> {
+        let path = self.path(task);
+        fs::write(&path, contents).map_err(|source| TasknError::Note { path, source })
+    }
+
+    /// Moves a note from one path to another, creating the destination's directory if needed.
+    pub fn move_note(&self, from: &Path, to: &Path) -> Result<()> {
+        let note_error = |source| TasknError::Note {
+            path: to.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = to.parent() {
+            create_dir_all(parent).map_err(note_error)?;
+        }
+        fs::rename(from, to).map_err(note_error)
+    }
+
+    /// Sets or clears the `+taskn` tag on `task`, depending on whether or not it has a note.
+    /// Returns whether the tag changed.
+    pub fn sync_tag(&self, task: &mut Task) -> Result<bool> {
+        let has_note = self.has_note(task)?;
+        let has_tag = task.has_tag("taskn");
+        if has_note && !has_tag {
+            task.tags
+                .get_or_insert_with(Vec::new)
+                .push("taskn".to_string());
+        } else if !has_note && has_tag {
+            let tags = task
+                .tags
+                .take()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|tag| tag != "taskn")
+                .collect::<Vec<String>>();
+            task.tags = if tags.is_empty() { None } else { Some(tags) };
+        }
+        Ok(has_note != has_tag)
+    }
+}
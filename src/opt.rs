@@ -1,45 +1,169 @@
+use std::collections::BTreeMap;
 use std::env;
-use std::str::FromStr;
+use std::io::Write;
 
+use structopt::clap::Shell;
 use structopt::StructOpt;
 
-use crate::commands::Command;
+use crate::commands::{
+    completions, edit, history, hook, interactive, migrate, order, remind, search, Command,
+};
+use crate::config::{Config, KeyBindings, ListSettings, Settings};
+use crate::error::{Result, TasknError};
+use crate::journal::Journal;
+use crate::notes::NoteStore;
+use crate::ordering::{List, SortRule};
+use crate::taskwarrior::{filter, TaskBackend, Taskrc};
 
 #[derive(StructOpt)]
 #[structopt(name = "taskn", about = "Taskwarrior task annotation helper")]
 struct ProtoOpt {
     /// The editor used to open task notes. If unset, taskn will attempt to use $EDITOR. If $EDITOR
     /// is also unset, taskn will use vi.
-    #[structopt(long)]
+    #[structopt(long, global = true)]
     editor: Option<String>,
 
-    /// The file format used for task notes.
-    #[structopt(long, default_value = "md")]
-    file_format: String,
+    /// The file format used for task notes [default: md].
+    #[structopt(long, global = true)]
+    file_format: Option<String>,
 
     /// The directory in which task notes are placed. If the directory does not already exist,
-    /// taskn will create it.
-    #[structopt(long, default_value = "~/.taskn")]
-    root_dir: String,
+    /// taskn will create it [default: ~/.taskn].
+    #[structopt(long, global = true)]
+    root_dir: Option<String>,
 
-    #[structopt(default_value = "edit")]
-    command: String,
+    /// The profile from taskn's configuration file to use.
+    #[structopt(long, global = true)]
+    profile: Option<String>,
 
-    /// Any remaining arguments are passed along to taskwarrior while selecting tasks.
-    args: Vec<String>,
+    /// The configuration file to use [default: ~/.config/taskn/config.toml].
+    #[structopt(long, global = true)]
+    config: Option<String>,
+
+    /// Defaults to `edit`. A bare filter (e.g. `taskn 16` or `taskn +home`) is shorthand for
+    /// `taskn edit <filter>`.
+    #[structopt(subcommand)]
+    command: Option<Subcommand>,
+}
+
+#[derive(StructOpt)]
+enum Subcommand {
+    /// Opens the notes of every task matching the filter.
+    Edit(edit::Args),
+
+    /// Browses and reorders pending tasks alongside their notes.
+    Interactive(interactive::Args),
+
+    /// Moves a task to a new position among the pending tasks, or sorts them by a rule.
+    Order(order::Args),
+
+    /// Syncs tasks tagged +remindme to the operating system's reminders.
+    Remind(remind::Args),
+
+    /// Searches the notes for words, and lists the matching tasks.
+    Search(search::Args),
+
+    /// Runs as a Taskwarrior hook. Installed by `taskn init`.
+    Hook(hook::Args),
+
+    /// Defines the UDAs taskn uses and installs its Taskwarrior hooks.
+    Init,
+
+    /// Moves the task order kept by older versions of taskn in `estimate` over to `taskn_order`.
+    Migrate(migrate::Args),
+
+    /// Rolls back the most recent taskn operation, e.g. a whole reorder.
+    Undo,
+
+    /// Lists the most recent taskn operations.
+    History(history::Args),
+
+    /// Prints a completion script for the provided shell.
+    Completions(completions::Args),
+
+    #[structopt(external_subcommand)]
+    Filter(Vec<String>),
+}
+
+impl Subcommand {
+    /// Turns the subcommand into the [Command] to run.
+    fn into_command(self) -> Result<Command> {
+        use Subcommand::*;
+        Ok(match self {
+            Edit(args) => Command::Edit(args),
+            Interactive(args) => Command::Interactive(args),
+            Order(args) => Command::Order(args),
+            Remind(args) => Command::Remind(args),
+            Search(args) => Command::Search(args),
+            Hook(args) => Command::Hook(args),
+            Init => Command::Init,
+            Migrate(args) => Command::Migrate(args),
+            Undo => Command::Undo,
+            History(args) => Command::History(args),
+            Completions(args) => Command::Completions(args),
+            Filter(filter) => {
+                if !filter::looks_like_filter(&filter[0]) {
+                    return Err(TasknError::Invalid(format!(
+                        "unknown command '{}' (use `taskn edit {}` to select tasks by description)",
+                        filter[0],
+                        filter.join(" ")
+                    )));
+                }
+                Command::Edit(edit::Args {
+                    filter,
+                    ..edit::Args::default()
+                })
+            }
+        })
+    }
 }
 
+#[derive(Default)]
 pub struct Opt {
     pub editor: String,
     pub file_format: String,
     pub root_dir: String,
-    pub command: Command,
-    pub args: Vec<String>,
+    /// The taskrc to run `task` with, if it shouldn't use its default.
+    pub taskrc: Option<String>,
+    /// Overrides Taskwarrior's `data.location`.
+    pub data_location: Option<String>,
+    pub keys: KeyBindings,
+    /// The named orderings from the configuration file.
+    pub lists: BTreeMap<String, ListSettings>,
+    /// The named sort rules from the configuration file.
+    pub sorts: BTreeMap<String, String>,
+    /// Whether to add front matter to notes which don't have it yet.
+    pub front_matter: bool,
+    /// Whether to sync checklists in notes with tasks.
+    pub checklists: bool,
+    /// The configuration file picked with `--config`, if any.
+    pub config: Option<String>,
+    /// The profile picked with `--profile`, if any.
+    pub profile: Option<String>,
 }
 
 impl Opt {
-    fn from_proto_opt(proto_opt: ProtoOpt) -> Self {
-        let editor = if let Some(editor) = proto_opt.editor {
+    fn from_proto_opt(proto_opt: ProtoOpt, config: &Config) -> Result<(Self, Command)> {
+        let mut command = match proto_opt.command {
+            None => Command::Edit(edit::Args::default()),
+            Some(subcommand) => subcommand.into_command()?,
+        };
+
+        let cli = Settings {
+            editor: proto_opt.editor,
+            file_format: proto_opt.file_format,
+            root_dir: proto_opt.root_dir,
+            ..Settings::default()
+        };
+        let (settings, default_args) =
+            config.resolve(cli, proto_opt.profile.as_deref(), command.name())?;
+        if let Some(filter) = command.filter_mut() {
+            if filter.is_empty() {
+                *filter = default_args;
+            }
+        }
+
+        let editor = if let Some(editor) = settings.editor {
             editor
         } else if let Ok(editor) = env::var("EDITOR") {
             editor
@@ -47,31 +171,247 @@ impl Opt {
             "vi".to_string()
         };
 
-        let root_dir = shellexpand::tilde(&proto_opt.root_dir).to_string();
+        let root_dir = settings.root_dir.unwrap_or_else(|| "~/.taskn".to_string());
+        let expand = |path: String| shellexpand::tilde(&path).to_string();
+
+        let opt = Opt {
+            editor,
+            file_format: settings.file_format.unwrap_or_else(|| "md".to_string()),
+            root_dir: expand(root_dir),
+            taskrc: settings.taskrc.map(expand),
+            data_location: settings.data_location.map(expand),
+            keys: config.key_bindings().map_err(TasknError::Invalid)?,
+            lists: config.lists.clone(),
+            sorts: config.sorts.clone(),
+            front_matter: settings.front_matter.unwrap_or(false),
+            checklists: settings.checklists.unwrap_or(false),
+            config: proto_opt.config.map(expand),
+            profile: proto_opt.profile,
+        };
+        Ok((opt, command))
+    }
+
+    /// The [Journal] of changes made to tasks, kept in `--root-dir`.
+    pub fn journal(&self) -> Journal {
+        Journal::new(&self.root_dir)
+    }
+
+    /// The [NoteStore] described by `--root-dir` and `--file-format`.
+    pub fn notes(&self) -> NoteStore {
+        NoteStore::new(&self.root_dir, &self.file_format)
+    }
+
+    /// The ordering to use: the list `name` picked with `--list`, or else a configured list whose
+    /// filter is `filter` or whose Taskwarrior context is active, or else the default list. The
+    /// list is narrowed down to the tasks matching `filter`.
+    pub fn list(
+        &self,
+        backend: &dyn TaskBackend,
+        name: Option<&str>,
+        filter: &[String],
+    ) -> Result<List> {
+        let name = match name {
+            Some(name) => Some(name.to_string()),
+            None => self.matching_list(backend, filter)?,
+        };
+        let list = match name {
+            None => List::default(),
+            Some(name) => {
+                let settings = self.lists.get(&name).cloned().unwrap_or_default();
+                let mut list_filter = settings.filter;
+                if let Some(context) = settings.context {
+                    list_filter.push(context_filter(&Taskrc::load(backend)?, &context)?);
+                }
+                List::named(&name, list_filter)?
+            }
+        };
+        Ok(list.with_filter(filter))
+    }
+
+    /// The rule picked with `--by`, which is either the name of a rule from the configuration
+    /// file or a rule itself.
+    pub fn sort_rule(&self, by: &str) -> Result<SortRule> {
+        self.sorts
+            .get(by)
+            .map(String::as_str)
+            .unwrap_or(by)
+            .parse::<SortRule>()
+    }
 
-        let command;
-        let args;
-        match Command::from_str(&proto_opt.command) {
-            Ok(cmd) => {
-                command = cmd;
-                args = proto_opt.args;
+    /// Finds the configured list whose filter is `filter`, or else whose context is active.
+    fn matching_list(
+        &self,
+        backend: &dyn TaskBackend,
+        filter: &[String],
+    ) -> Result<Option<String>> {
+        if !filter.is_empty() {
+            if let Some((name, _)) = self.lists.iter().find(|(_, list)| list.filter == filter) {
+                return Ok(Some(name.clone()));
             }
-            Err(_) => {
-                command = Command::Edit;
-                args = [&[proto_opt.command], &proto_opt.args[..]].concat();
+        }
+        if self.lists.values().all(|list| list.context.is_none()) {
+            return Ok(None);
+        }
+
+        let taskrc = Taskrc::load(backend)?;
+        let active = match taskrc.get("context") {
+            None => return Ok(None),
+            Some(active) => active,
+        };
+        Ok(self
+            .lists
+            .iter()
+            .find(|(_, list)| list.context.as_deref() == Some(active))
+            .map(|(name, _)| name.clone()))
+    }
+
+    /// Parses the command line, returning the options along with the command to run.
+    pub fn from_args() -> Result<(Self, Command)> {
+        let proto_opt = ProtoOpt::from_args();
+        let event = match &proto_opt.command {
+            Some(Subcommand::Hook(args)) => Some(args.event),
+            _ => None,
+        };
+        let resolved = Config::load(proto_opt.config.as_deref())
+            .and_then(|config| Self::from_proto_opt(proto_opt, &config));
+        match (resolved, event) {
+            // a hook which fails blocks the Taskwarrior command it runs in, so hooks still run,
+            // and pass the error on instead
+            (Err(e), Some(event)) => {
+                let args = hook::Args {
+                    event,
+                    setup_error: Some(e),
+                };
+                Ok((Opt::default(), Command::Hook(args)))
             }
+            (resolved, _) => resolved,
         }
+    }
+}
 
-        Opt {
-            editor,
-            file_format: proto_opt.file_format,
-            root_dir,
-            command,
-            args,
+/// Looks up the filter of a Taskwarrior context. Taskwarrior 2.6 keeps it in
+/// `context.<name>.read`, and earlier versions in `context.<name>`.
+fn context_filter(taskrc: &Taskrc, context: &str) -> Result<String> {
+    taskrc
+        .get(&format!("context.{}.read", context))
+        .or_else(|| taskrc.get(&format!("context.{}", context)))
+        .map(|filter| format!("({})", filter))
+        .ok_or_else(|| TasknError::Invalid(format!("no Taskwarrior context named '{}'", context)))
+}
+
+/// Writes a completion script for `shell` to `out`.
+pub fn write_completions<W: Write>(shell: Shell, out: &mut W) {
+    ProtoOpt::clap().gen_completions_to("taskn", shell, out);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+
+    use super::*;
+    use crate::commands::hook::Event;
+
+    /// Parses `args` as they'd be given after `taskn` on the command line.
+    fn parse(args: &[&str], config: &Config) -> Result<Command> {
+        let proto_opt = ProtoOpt::from_iter_safe(iter::once("taskn").chain(args.iter().copied()))
+            .map_err(|e| TasknError::Invalid(e.to_string()))?;
+        let (_, command) = Opt::from_proto_opt(proto_opt, config)?;
+        Ok(command)
+    }
+
+    #[test]
+    fn test_parse_typed_arguments() -> Result<()> {
+        let config = Config::default();
+        match parse(&["history", "5"], &config)? {
+            Command::History(args) => assert_eq!(args.count, 5),
+            _ => panic!("expected history"),
+        }
+        match parse(&["history"], &config)? {
+            Command::History(args) => assert_eq!(args.count, 20),
+            _ => panic!("expected history"),
+        }
+        match parse(&["order", "--list", "work", "3", "up", "2"], &config)? {
+            Command::Order(args) => {
+                assert_eq!(args.list.as_deref(), Some("work"));
+                assert_eq!(args.task.as_deref(), Some("3"));
+                assert_eq!(args.movement, vec!["up".to_string(), "2".to_string()]);
+            }
+            _ => panic!("expected order"),
+        }
+        match parse(&["search", "--open", "venue", "+home"], &config)? {
+            Command::Search(args) => {
+                assert!(args.open);
+                assert_eq!(args.query, "venue");
+                assert_eq!(args.filter, vec!["+home".to_string()]);
+            }
+            _ => panic!("expected search"),
         }
+        assert!(matches!(
+            parse(&["hook", "on-modify"], &config)?,
+            Command::Hook(args) if args.event == Event::OnModify
+        ));
+        assert!(matches!(
+            parse(&["completions", "ZSH"], &config)?,
+            Command::Completions(args) if matches!(args.shell, Shell::Zsh)
+        ));
+
+        for args in [
+            &["history", "five"][..],
+            &["order", "3"],
+            &["order", "--by", "due", "3", "0"],
+            &["search"],
+            &["hook", "on-delete"],
+            &["completions", "tcsh"],
+            &["interactive", "--dry-run"],
+        ]
+        .iter()
+        {
+            assert!(parse(args, &config).is_err(), "{:?} parsed", args);
+        }
+        Ok(())
     }
 
-    pub fn from_args() -> Self {
-        Self::from_proto_opt(ProtoOpt::from_args())
+    #[test]
+    fn test_parse_filters() -> Result<()> {
+        let config = toml::from_str::<Config>(
+            r#"
+            [commands.edit]
+            args = ["status:pending"]
+
+            [commands.order]
+            args = ["1", "top"]
+            "#,
+        )
+        .map_err(|e| TasknError::Invalid(e.to_string()))?;
+        let edit_filter = |command| match command {
+            Command::Edit(args) => args.filter,
+            _ => panic!("expected edit"),
+        };
+
+        // bare filters are shorthand for `edit`, and the configured filter is used without one
+        assert_eq!(
+            edit_filter(parse(&["+home", "16"], &config)?),
+            vec!["+home", "16"]
+        );
+        assert_eq!(edit_filter(parse(&[], &config)?), vec!["status:pending"]);
+        assert_eq!(
+            edit_filter(parse(&["edit"], &config)?),
+            vec!["status:pending"]
+        );
+        match parse(&["edit", "--template", "meeting", "+work"], &config)? {
+            Command::Edit(args) => {
+                assert_eq!(args.template.as_deref(), Some("meeting"));
+                assert_eq!(args.filter, vec!["+work".to_string()]);
+            }
+            _ => panic!("expected edit"),
+        }
+        assert!(parse(&["frobnicate"], &config).is_err());
+
+        // only filters are filled in from the configuration
+        match parse(&["order"], &config)? {
+            Command::Order(args) => assert!(args.task.is_none() && args.movement.is_empty()),
+            _ => panic!("expected order"),
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,708 @@
+//! taskn's orderings of pending tasks. The position of each task is stored in its `taskn_order`
+//! UDA, so that Taskwarrior reports can sort by it (e.g. `report.next.sort=taskn_order+`).
+//! Older versions of taskn stored it in `estimate` instead, which [migrate] moves over. Besides
+//! this default order, tasks can be ordered in any number of named [List]s, and a list can be
+//! sorted by a [SortRule] rather than by hand.
+//!
+//! Ranks are fractional, so that moving a task only rewrites that task: it gets a rank between
+//! its new neighbours' ranks (e.g. 2.5 between 2 and 3). Ranks are kept to a few decimal places,
+//! and once there's no room left between two neighbours every task is renumbered from 0.
+//!
+//! Saving the default list also puts `taskn_order+` first in `report.next.sort`, so that `task
+//! next` lists tasks in taskn's order too.
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{Task, TaskBackend, Taskrc, Transaction};
+
+/// The UDA which holds each task's rank in the default list.
+pub const UDA: &str = "taskn_order";
+
+/// The most decimal places a rank may have before the tasks are renumbered.
+const MAX_DECIMALS: i32 = 6;
+
+/// One of taskn's orderings. The default list orders every pending task, while a named list
+/// orders only the pending tasks matching its filter, and keeps its ranks in its own UDA
+/// (`taskn_order_<name>`). Only the default list changes how `task next` sorts; other reports
+/// can sort by a named list's UDA (e.g. `report.work.sort=taskn_order_work+`).
+#[derive(Clone, Debug, Default)]
+pub struct List {
+    name: Option<String>,
+    filter: Vec<String>,
+}
+
+impl List {
+    /// The list named `name`, made up of the pending tasks matching `filter`. List names may only
+    /// contain letters, digits, and underscores, since they become part of a UDA's name.
+    pub fn named(name: &str, filter: Vec<String>) -> Result<Self> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err(TasknError::Invalid(format!(
+                "invalid list name '{}' (use letters, digits, and underscores)",
+                name
+            )));
+        }
+        Ok(Self {
+            name: Some(name.to_string()),
+            ..Self::default()
+        }
+        .with_filter(&filter))
+    }
+
+    /// Narrows the list down to the tasks which also match `filter`.
+    pub fn with_filter<S: AsRef<str>>(mut self, filter: &[S]) -> Self {
+        if !filter.is_empty() {
+            self.filter.push("(".to_string());
+            self.filter
+                .extend(filter.iter().map(|arg| arg.as_ref().to_string()));
+            self.filter.push(")".to_string());
+        }
+        self
+    }
+
+    /// The list's name, which is `default` for the default list.
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("default")
+    }
+
+    /// Whether this is a named list rather than the default list.
+    pub fn is_named(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// The UDA which holds each task's rank in this list.
+    pub fn uda(&self) -> String {
+        match &self.name {
+            None => UDA.to_string(),
+            Some(name) => format!("{}_{}", UDA, name),
+        }
+    }
+
+    /// Loads every pending task in the list, in order. Tasks which haven't been ordered yet come
+    /// first.
+    pub fn load(&self, backend: &dyn TaskBackend) -> Result<Vec<Task>> {
+        let mut filter = vec!["status:pending".to_string()];
+        filter.extend(self.filter.iter().cloned());
+        let mut tasks = backend.export(&filter)?;
+
+        let uda = self.uda();
+        tasks.sort_by(|a, b| {
+            a.rank(&uda)
+                .partial_cmp(&b.rank(&uda))
+                .unwrap_or(Ordering::Equal)
+        });
+        Ok(tasks)
+    }
+
+    /// Saves `tasks` in the order provided. Only the tasks whose rank has to change are written,
+    /// and if any of them can't be written, none of them are.
+    pub fn save(&self, backend: &dyn TaskBackend, tasks: &mut [Task]) -> Result<()> {
+        let transaction = self.transaction(tasks);
+        self.define_uda(backend)?;
+        transaction.commit(backend)?;
+        Ok(())
+    }
+
+    /// Defines the list's UDA. For the default list, this also makes `task next` sort by it.
+    pub fn define_uda(&self, backend: &dyn TaskBackend) -> Result<()> {
+        let uda = self.uda();
+        let mut taskrc = Taskrc::load(backend)?;
+        let label = match &self.name {
+            None => "Order".to_string(),
+            Some(name) => format!("Order ({})", name),
+        };
+        taskrc.define_uda(&uda, "numeric", Some(&label))?;
+        if self.is_named() {
+            return Ok(());
+        }
+
+        taskrc.sort_report("next", &format!("{}+", uda))?;
+        Ok(())
+    }
+
+    /// Ranks `tasks` in the order provided and queues the tasks whose rank changed on a
+    /// [Transaction], for when the caller wants to inspect the changes before (or instead of)
+    /// saving them.
+    pub fn transaction(&self, tasks: &mut [Task]) -> Transaction {
+        let uda = self.uda();
+        let before = tasks
+            .iter()
+            .map(|task| task.rank(&uda))
+            .collect::<Vec<Option<f64>>>();
+        self.rerank(tasks);
+        tasks
+            .iter()
+            .zip(before)
+            .filter(|(task, before)| task.rank(&uda) != *before)
+            .map(|(task, _)| task.clone())
+            .collect()
+    }
+
+    /// Gives `tasks` strictly increasing ranks in the order provided, changing as few ranks as
+    /// possible. See [rerank].
+    pub fn rerank(&self, tasks: &mut [Task]) {
+        let uda = self.uda();
+        let mut ranks = tasks
+            .iter()
+            .map(|task| task.rank(&uda))
+            .collect::<Vec<Option<f64>>>();
+        rerank(&mut ranks);
+        for (task, rank) in tasks.iter_mut().zip(ranks) {
+            task.set_rank(&uda, rank);
+        }
+    }
+}
+
+/// Builds the order that older versions of taskn stored in `estimate`. Pending tasks which
+/// already have a position keep it, and tasks which only have an estimate are added after them,
+/// with their estimate cleared. Returns the tasks in their new order, ready for [List::save], along
+/// with how many tasks were migrated.
+pub fn migrate(backend: &dyn TaskBackend) -> Result<(Vec<Task>, usize)> {
+    let (mut ordered, mut unordered): (Vec<Task>, Vec<Task>) = List::default()
+        .load(backend)?
+        .into_iter()
+        .partition(|task| task.taskn_order.is_some());
+
+    let mut migrated = unordered
+        .iter()
+        .filter(|task| task.estimate.is_some())
+        .cloned()
+        .collect::<Vec<Task>>();
+    unordered.retain(|task| task.estimate.is_none());
+    migrated.sort_by_key(|task| task.estimate);
+    for task in migrated.iter_mut() {
+        task.estimate = None;
+    }
+
+    let count = migrated.len();
+    ordered.extend(migrated);
+    // tasks which weren't ordered at all stay that way, at the top
+    unordered.extend(ordered);
+    Ok((unordered, count))
+}
+
+/// Gives `ranks` strictly increasing values, changing as few of them as possible. The longest
+/// run of ranks which are already in order is kept, and every other rank is placed between its
+/// neighbours. If there's no room between two neighbours, every rank is renumbered from 0.
+fn rerank(ranks: &mut [Option<f64>]) {
+    let keep = longest_increasing(ranks);
+
+    let mut start = 0;
+    while start < ranks.len() {
+        if keep[start] {
+            start += 1;
+            continue;
+        }
+        let end = (start..ranks.len())
+            .find(|i| keep[*i])
+            .unwrap_or(ranks.len());
+        let lo = if start > 0 { ranks[start - 1] } else { None };
+        let hi = if end < ranks.len() { ranks[end] } else { None };
+        match ranks_between(lo, hi, end - start) {
+            Some(new_ranks) => {
+                for (rank, new_rank) in ranks[start..end].iter_mut().zip(new_ranks) {
+                    *rank = Some(new_rank);
+                }
+            }
+            None => {
+                for (i, rank) in ranks.iter_mut().enumerate() {
+                    *rank = Some(i as f64);
+                }
+                return;
+            }
+        }
+        start = end;
+    }
+}
+
+/// Marks the ranks which make up the longest strictly increasing subsequence of `ranks`, ignoring
+/// tasks which have no rank.
+fn longest_increasing(ranks: &[Option<f64>]) -> Vec<bool> {
+    // lengths[i] is the length of the longest increasing run ending at i, and previous[i] is the
+    // index before i in that run
+    let mut lengths = vec![0; ranks.len()];
+    let mut previous = vec![None; ranks.len()];
+    for i in 0..ranks.len() {
+        let rank = match ranks[i] {
+            None => continue,
+            Some(rank) => rank,
+        };
+        lengths[i] = 1;
+        for j in 0..i {
+            if matches!(ranks[j], Some(other) if other < rank) && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut keep = vec![false; ranks.len()];
+    let mut current = (0..ranks.len())
+        .filter(|i| lengths[*i] > 0)
+        .max_by_key(|i| lengths[*i]);
+    while let Some(i) = current {
+        keep[i] = true;
+        current = previous[i];
+    }
+    keep
+}
+
+/// Picks `count` increasing ranks between `lo` and `hi`, either of which may be open-ended.
+/// Returns `None` if they don't fit within [MAX_DECIMALS].
+fn ranks_between(lo: Option<f64>, hi: Option<f64>, count: usize) -> Option<Vec<f64>> {
+    let (lo, hi) = match (lo, hi) {
+        (None, None) => return Some((0..count).map(|i| i as f64).collect()),
+        (Some(lo), None) => return Some((1..=count).map(|i| lo + i as f64).collect()),
+        (None, Some(hi)) => return Some((0..count).rev().map(|i| hi - (i + 1) as f64).collect()),
+        (Some(lo), Some(hi)) => (lo, hi),
+    };
+
+    // each rank gets its own slice of the gap, centered on an even split of it
+    let width = (hi - lo) / (2 * count + 2) as f64;
+    (0..count)
+        .map(|i| {
+            let from = lo + width * (2 * i + 1) as f64;
+            shortest_between(from, from + width * 2.0)
+        })
+        .collect()
+}
+
+/// Finds the number with the fewest decimal places strictly between `lo` and `hi`.
+fn shortest_between(lo: f64, hi: f64) -> Option<f64> {
+    (0..=MAX_DECIMALS).find_map(|decimals| {
+        let scale = 10f64.powi(decimals);
+        let candidate = ((lo + hi) / 2.0 * scale).round() / scale;
+        if lo < candidate && candidate < hi {
+            Some(candidate)
+        } else {
+            None
+        }
+    })
+}
+
+/// Where `taskn order` moves a task to.
+#[derive(Debug, PartialEq)]
+pub enum Movement {
+    /// An absolute position, starting from 0.
+    To(usize),
+    Top,
+    Bottom,
+    Up(usize),
+    Down(usize),
+    /// Just before another task, given as an ID, UUID or short UUID.
+    Before(String),
+    /// Just after another task, given as an ID, UUID or short UUID.
+    After(String),
+}
+
+impl Movement {
+    /// Parses the arguments which follow the task in `taskn order`, e.g. `top`, `up 3`, `after 7`
+    /// or a position.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+        let count = |arg: &str| {
+            arg.parse::<usize>()
+                .map_err(|_| TasknError::Invalid(format!("invalid number of steps '{}'", arg)))
+        };
+        match args.as_slice() {
+            ["top"] => Ok(Movement::Top),
+            ["bottom"] => Ok(Movement::Bottom),
+            ["up"] => Ok(Movement::Up(1)),
+            ["down"] => Ok(Movement::Down(1)),
+            ["up", steps] => Ok(Movement::Up(count(steps)?)),
+            ["down", steps] => Ok(Movement::Down(count(steps)?)),
+            ["before", task] => Ok(Movement::Before(task.to_string())),
+            ["after", task] => Ok(Movement::After(task.to_string())),
+            [position] => position.parse().map(Movement::To).map_err(|_| {
+                TasknError::Invalid(format!("invalid position '{}'", position))
+            }),
+            _ => Err(TasknError::Invalid(
+                "usage: taskn order <task> <position|top|bottom|up [n]|down [n]|before <task>|after <task>>"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// Moves the task referred to by `reference` as described by `movement`, shifting the tasks in
+/// between. Returns the task's new position.
+pub fn move_task(tasks: &mut Vec<Task>, reference: &str, movement: &Movement) -> Result<usize> {
+    let index = find_task(tasks, reference)?;
+    let last = tasks.len() - 1;
+    let position = match movement {
+        Movement::To(position) if *position > last => {
+            return Err(TasknError::Invalid(format!(
+                "position {} is out of range, there are {} pending tasks",
+                position,
+                tasks.len()
+            )))
+        }
+        Movement::To(position) => *position,
+        Movement::Top => 0,
+        Movement::Bottom => last,
+        Movement::Up(steps) => index.saturating_sub(*steps),
+        Movement::Down(steps) => index.saturating_add(*steps).min(last),
+        Movement::Before(other) | Movement::After(other) => {
+            let other = find_task(tasks, other)?;
+            if other == index {
+                return Err(TasknError::Invalid(format!(
+                    "can't move task '{}' relative to itself",
+                    reference
+                )));
+            }
+            // positions after the task shift up by one once it's taken out
+            let other = if other > index { other - 1 } else { other };
+            match movement {
+                Movement::After(_) => other + 1,
+                _ => other,
+            }
+        }
+    };
+
+    let task = tasks.remove(index);
+    tasks.insert(position, task);
+    Ok(position)
+}
+
+/// Finds the task in `tasks` with the ID, UUID or short UUID `reference`. IDs are tried first,
+/// and a short UUID must only match one task.
+pub fn find_task(tasks: &[Task], reference: &str) -> Result<usize> {
+    if let Ok(id) = reference.parse::<usize>() {
+        if let Some(index) = tasks.iter().position(|task| task.id == Some(id)) {
+            return Ok(index);
+        }
+    }
+
+    let prefix = reference.to_lowercase();
+    let matches = tasks
+        .iter()
+        .enumerate()
+        .filter(|(_, task)| !prefix.is_empty() && task.uuid.starts_with(&prefix))
+        .map(|(index, _)| index)
+        .collect::<Vec<usize>>();
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(TasknError::Invalid(format!(
+            "no pending task matching '{}'",
+            reference
+        ))),
+        _ => Err(TasknError::Invalid(format!(
+            "'{}' matches more than one task",
+            reference
+        ))),
+    }
+}
+
+/// Tasks with this tag keep their positions when a list is sorted by a [SortRule].
+pub const PINNED_TAG: &str = "pinned";
+
+/// Computes an order from the tasks' attributes, e.g. `due,priority-,+next,project`. Each key is
+/// an attribute, sorted ascending (or descending when it ends in `-`), or a tag starting with `+`,
+/// which puts the tasks with that tag first. Later keys break ties in earlier ones, and tasks
+/// without an attribute come after the tasks which have it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortRule {
+    keys: Vec<SortKey>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SortKey {
+    Attribute { name: String, descending: bool },
+    Tag(String),
+}
+
+impl FromStr for SortRule {
+    type Err = TasknError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let keys = s
+            .split(',')
+            .map(|key| {
+                let key = key.trim();
+                let (key, descending) = match key.strip_suffix('-') {
+                    Some(key) => (key, true),
+                    None => (key.strip_suffix('+').unwrap_or(key), false),
+                };
+                let key = match key.strip_prefix('+') {
+                    Some(tag) => SortKey::Tag(tag.to_string()),
+                    None => SortKey::Attribute {
+                        name: key.to_string(),
+                        descending,
+                    },
+                };
+                match &key {
+                    SortKey::Tag(name) | SortKey::Attribute { name, .. }
+                        if name.is_empty() || !name.chars().all(is_attribute_char) =>
+                    {
+                        Err(TasknError::Invalid(format!(
+                            "invalid sort rule '{}', expected e.g. 'due,priority-,+next'",
+                            s
+                        )))
+                    }
+                    _ => Ok(key),
+                }
+            })
+            .collect::<Result<Vec<SortKey>>>()?;
+        Ok(SortRule { keys })
+    }
+}
+
+impl SortRule {
+    /// Sorts `tasks` by the rule. Tasks tagged [PINNED_TAG] stay where they are, and the other
+    /// tasks are sorted into the positions around them.
+    pub fn sort(&self, tasks: &mut Vec<Task>) -> Result<()> {
+        let mut pinned = Vec::new();
+        let mut sorted = Vec::new();
+        for (position, task) in tasks.drain(..).enumerate() {
+            if task.has_tag(PINNED_TAG) {
+                pinned.push((position, task));
+            } else {
+                let values = self.values(&task)?;
+                sorted.push((values, task));
+            }
+        }
+
+        // a stable sort, so that ties keep their current order
+        sorted.sort_by(|(a, _), (b, _)| self.compare(a, b));
+        tasks.extend(sorted.into_iter().map(|(_, task)| task));
+        for (position, task) in pinned {
+            tasks.insert(position, task);
+        }
+        Ok(())
+    }
+
+    /// Looks up the value of each key on `task`.
+    fn values(&self, task: &Task) -> Result<Vec<Option<Value>>> {
+        let attributes = task.attributes()?;
+        Ok(self
+            .keys
+            .iter()
+            .map(|key| match key {
+                SortKey::Tag(tag) => Some(Value::Bool(task.has_tag(tag))),
+                SortKey::Attribute { name, .. } => match name.as_str() {
+                    "id" => task.id.map(Value::from),
+                    "urgency" => task.urgency.map(Value::from),
+                    // H, M, L sort in order of importance, rather than alphabetically
+                    "priority" => task
+                        .priority
+                        .as_deref()
+                        .and_then(|priority| ["L", "M", "H"].iter().position(|p| *p == priority))
+                        .map(Value::from),
+                    name => attributes.get(name).cloned(),
+                },
+            })
+            .collect())
+    }
+
+    fn compare(&self, a: &[Option<Value>], b: &[Option<Value>]) -> Ordering {
+        for (key, (a, b)) in self.keys.iter().zip(a.iter().zip(b)) {
+            let ordering = match (a, b) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => match key {
+                    // tagged tasks first
+                    SortKey::Tag(_) => compare_values(b, a),
+                    SortKey::Attribute {
+                        descending: true, ..
+                    } => compare_values(b, a),
+                    SortKey::Attribute { .. } => compare_values(a, b),
+                },
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl fmt::Display for SortRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, key) in self.keys.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match key {
+                SortKey::Tag(tag) => write!(f, "+{}", tag)?,
+                SortKey::Attribute { name, descending } => {
+                    write!(f, "{}{}", name, if *descending { "-" } else { "" })?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_attribute_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Compares two attribute values, numerically when both are numbers. UDAs read from
+/// Taskwarrior's data files are strings even when they hold numbers, so those are parsed first.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    let number = |value: &Value| match value {
+        Value::Number(number) => number.as_f64(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => match (a, b) {
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (a, b) => a.to_string().cmp(&b.to_string()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::task;
+
+    fn ranked(ranks: &[Option<f64>]) -> Vec<Task> {
+        ranks
+            .iter()
+            .enumerate()
+            .map(|(i, rank)| Task {
+                taskn_order: *rank,
+                ..task(i + 1)
+            })
+            .collect()
+    }
+
+    fn ranks(tasks: &[Task]) -> Vec<f64> {
+        tasks.iter().map(|task| task.taskn_order.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_move_rewrites_one_task() -> Result<()> {
+        // the last task was moved between the first two
+        let mut tasks = ranked(&[Some(0.0), Some(3.0), Some(1.0), Some(2.0)]);
+        let backend = MemoryBackend::new(tasks.clone());
+        let plan = List::default().transaction(&mut tasks).plan(&backend)?;
+        assert_eq!(ranks(&tasks), vec![0.0, 0.5, 1.0, 2.0]);
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].after.taskn_order, Some(0.5));
+
+        let mut tasks = ranked(&[None, Some(0.0), None, None, Some(1.0), None]);
+        List::default().rerank(&mut tasks);
+        assert_eq!(ranks(&tasks), vec![-1.0, 0.0, 0.3, 0.7, 1.0, 2.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rerank_renumbers_without_room() {
+        let mut tasks = ranked(&[Some(0.0), Some(0.000002), Some(0.000001)]);
+        List::default().rerank(&mut tasks);
+        assert_eq!(ranks(&tasks), vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_named_list() -> Result<()> {
+        let mut tasks = ranked(&[Some(0.0), Some(1.0), Some(2.0)]);
+        tasks[1].project = Some("work".to_string());
+        tasks[2].project = Some("work".to_string());
+        let backend = MemoryBackend::new(tasks);
+
+        let list = List::named("work", vec!["project:work".to_string()])?;
+        let mut work = list.load(&backend)?;
+        assert_eq!(work.len(), 2);
+        work.swap(0, 1);
+        list.save(&backend, &mut work)?;
+
+        let work = list.load(&backend)?;
+        assert_eq!(work[0].uuid, backend.tasks()[2].uuid);
+        assert_eq!(work[0].rank("taskn_order_work"), Some(0.0));
+        // the default order is left alone
+        assert_eq!(ranks(&List::default().load(&backend)?), vec![0.0, 1.0, 2.0]);
+        assert!(List::named("not-a-uda", vec![]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_task() -> Result<()> {
+        let args = |args: &str| {
+            Movement::parse(
+                &args
+                    .split(' ')
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<String>>(),
+            )
+        };
+        let moved = |reference: &str, movement: &str| -> Result<Vec<usize>> {
+            let mut tasks = (0..5)
+                .map(|i| Task {
+                    id: Some(i + 1),
+                    uuid: format!("{:08x}-0000-4000-8000-000000000000", i * 0x11),
+                    ..Task::default()
+                })
+                .collect::<Vec<Task>>();
+            move_task(&mut tasks, reference, &args(movement)?)?;
+            Ok(tasks.iter().map(|task| task.id.unwrap()).collect())
+        };
+
+        assert_eq!(moved("4", "top")?, vec![4, 1, 2, 3, 5]);
+        assert_eq!(moved("2", "bottom")?, vec![1, 3, 4, 5, 2]);
+        assert_eq!(moved("4", "up 2")?, vec![1, 4, 2, 3, 5]);
+        assert_eq!(moved("4", "up 9")?, vec![4, 1, 2, 3, 5]);
+        assert_eq!(moved("1", "down")?, vec![2, 1, 3, 4, 5]);
+        assert_eq!(moved("1", "after 4")?, vec![2, 3, 4, 1, 5]);
+        assert_eq!(moved("5", "before 2")?, vec![1, 5, 2, 3, 4]);
+        assert_eq!(moved("00000033", "2")?, vec![1, 2, 4, 3, 5]);
+        assert_eq!(
+            moved("00000044-0000-4000-8000-000000000000", "0")?,
+            vec![5, 1, 2, 3, 4]
+        );
+
+        // every UUID starts with 0000
+        assert!(moved("0000", "top").is_err());
+        assert!(moved("9", "top").is_err());
+        assert!(moved("1", "5").is_err());
+        assert!(moved("1", "before 1").is_err());
+        assert!(args("sideways").is_err());
+        assert!(args("up one").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_rule() -> Result<()> {
+        let task = |id: usize, due: Option<&str>, priority: Option<&str>, tags: &[&str]| Task {
+            id: Some(id),
+            due: due.map(|due| due.parse().unwrap()),
+            priority: priority.map(|priority| priority.to_string()),
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            ..Task::default()
+        };
+        let mut tasks = vec![
+            task(1, None, Some("L"), &[]),
+            task(2, Some("20210301T000000Z"), None, &[]),
+            task(3, None, Some("H"), &["pinned"]),
+            task(4, Some("20210101T000000Z"), Some("L"), &[]),
+            task(5, None, Some("H"), &[]),
+            task(6, None, Some("L"), &["next"]),
+        ];
+        "due,priority-,+next"
+            .parse::<SortRule>()?
+            .sort(&mut tasks)?;
+        let ids = tasks
+            .iter()
+            .map(|task| task.id.unwrap())
+            .collect::<Vec<usize>>();
+        assert_eq!(ids, vec![4, 2, 3, 5, 6, 1]);
+        assert_eq!(
+            "due+, priority-,+next".parse::<SortRule>()?.to_string(),
+            "due,priority-,+next"
+        );
+
+        assert!("due,,project".parse::<SortRule>().is_err());
+        assert!("due;".parse::<SortRule>().is_err());
+        Ok(())
+    }
+}
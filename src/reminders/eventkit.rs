@@ -2,6 +2,7 @@
 //! This module allows one to read, create, and update reminders on macOS.
 //! Because this module depends on eventkit-sys, do not expect it to compile on non-macOS systems.
 use std::ffi::{c_void, CString};
+use std::fmt;
 use std::os::raw::c_char;
 use std::ptr::null_mut;
 use std::slice;
@@ -34,6 +35,16 @@ pub enum EKError {
     NotFound,
 }
 
+impl fmt::Display for EKError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EKError::NoAccess => write!(f, "taskn does not have access to reminders"),
+            EKError::NSError(desc) => write!(f, "{}", desc),
+            EKError::NotFound => write!(f, "reminder not found"),
+        }
+    }
+}
+
 impl EKError {
     /// The caller of this function must ensure that the *mut Object provided is, in fact, an
     /// NSError nad not some other kind of Object.
@@ -0,0 +1,47 @@
+//! Syncs tasks to reminders in macOS's Reminders app, through EventKit. Each task remembers the
+//! reminder it was synced to in the `taskn_reminder_uuid` UDA.
+mod eventkit;
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{Task, TaskBackend, Taskrc};
+use eventkit::{EKError, EventStore, Reminder};
+
+/// Defines the UDA which stores the UUID of a task's reminder, if it isn't defined already.
+pub fn define_uda(backend: &dyn TaskBackend) -> Result<()> {
+    Taskrc::load(backend)?.define_uda("taskn_reminder_uuid", "string", None)
+}
+
+/// Creates or updates a reminder for each task, titled after the task and due when the task
+/// stops waiting.
+pub fn sync(backend: &dyn TaskBackend, tasks: &mut [Task]) -> Result<()> {
+    define_uda(backend)?;
+
+    let task_len = tasks.len();
+    let mut event_store = EventStore::new_with_permission().map_err(reminder_error)?;
+    for (i, task) in tasks.iter_mut().enumerate() {
+        let mut reminder;
+        if let Some(taskn_reminder_uuid) = &task.taskn_reminder_uuid {
+            reminder = event_store
+                .get_reminder(taskn_reminder_uuid)
+                .map_err(reminder_error)?;
+        } else {
+            reminder = Reminder::new(&mut event_store);
+        }
+
+        reminder
+            .set_title(&task.description)
+            .set_notes(&task.uuid)
+            .set_alarm(task.wait.clone().map(|pdt| pdt.0));
+
+        event_store
+            .save_reminder(&reminder, i == task_len - 1)
+            .map_err(reminder_error)?;
+        task.set_reminder_uuid(backend, reminder.uuid())?;
+    }
+
+    Ok(())
+}
+
+fn reminder_error(e: EKError) -> TasknError {
+    TasknError::Reminder(e.to_string())
+}
@@ -0,0 +1,275 @@
+//! The search index, kept at `<root_dir>/.index.json`. For every stem it lists the notes which
+//! contain it and where, so that searching doesn't have to read every note. Each note's
+//! modification time and size are recorded too: opening the index re-reads only the notes which
+//! changed since, including notes changed outside of taskn, and drops the notes which are gone.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use super::{stem, words, Clause, Query};
+use crate::error::{Result, TasknError};
+use crate::notes::NoteStore;
+
+pub const INDEX_FILE: &str = ".index.json";
+
+/// Bumped whenever the way notes are indexed changes (e.g. the stemmer), so that older indexes
+/// are rebuilt rather than read.
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Default)]
+pub struct Index {
+    /// Where the index is saved, if anywhere.
+    path: Option<PathBuf>,
+    data: IndexData,
+    changed: bool,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct IndexData {
+    version: u32,
+    documents: BTreeMap<String, Document>,
+    /// The positions of each stem in each note, keyed by stem and then by the note's UUID.
+    postings: BTreeMap<String, BTreeMap<String, Vec<u32>>>,
+}
+
+#[derive(Default, Deserialize, Serialize, PartialEq)]
+struct Document {
+    /// Milliseconds since the epoch.
+    modified: u64,
+    size: u64,
+}
+
+impl Index {
+    /// Opens the index for `notes`, and brings it up to date with the notes. An index which can't
+    /// be read is rebuilt from scratch.
+    pub fn open(notes: &NoteStore) -> Result<Self> {
+        let path = notes.root_dir().join(INDEX_FILE);
+        let data = match fs::read_to_string(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => IndexData::default(),
+            Err(source) => return Err(TasknError::Note { path, source }),
+            Ok(contents) => serde_json::from_str::<IndexData>(&contents)
+                .ok()
+                .filter(|data| data.version == INDEX_VERSION)
+                .unwrap_or_default(),
+        };
+        let mut index = Index {
+            path: Some(path),
+            data,
+            changed: false,
+        };
+        index.refresh(notes)?;
+        if index.changed {
+            index.save()?;
+        }
+        Ok(index)
+    }
+
+    /// Re-reads the notes which changed since they were indexed.
+    fn refresh(&mut self, notes: &NoteStore) -> Result<()> {
+        let entries = notes.entries()?;
+        let gone = self
+            .data
+            .documents
+            .keys()
+            .filter(|uuid| !entries.iter().any(|(other, _)| other == *uuid))
+            .cloned()
+            .collect::<Vec<String>>();
+        for uuid in gone {
+            self.remove(&uuid);
+        }
+
+        for (uuid, path) in entries {
+            let note_error = |source| TasknError::Note {
+                path: path.clone(),
+                source,
+            };
+            let metadata = fs::metadata(&path).map_err(note_error)?;
+            let document = Document {
+                modified: metadata
+                    .modified()
+                    .map_err(note_error)?
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since| since.as_millis() as u64)
+                    .unwrap_or(0),
+                size: metadata.len(),
+            };
+            if self.data.documents.get(&uuid) == Some(&document) {
+                continue;
+            }
+            let contents = fs::read_to_string(&path).map_err(note_error)?;
+            self.add(&uuid, &contents);
+            self.data.documents.insert(uuid, document);
+        }
+        Ok(())
+    }
+
+    /// Indexes `contents` as the note for the task `uuid`, replacing what was indexed for it.
+    pub fn add(&mut self, uuid: &str, contents: &str) {
+        self.remove(uuid);
+        for (position, (_, word)) in words(contents).enumerate() {
+            self.data
+                .postings
+                .entry(stem(word))
+                .or_default()
+                .entry(uuid.to_string())
+                .or_default()
+                .push(position as u32);
+        }
+        self.data.documents.entry(uuid.to_string()).or_default();
+        self.changed = true;
+    }
+
+    /// Drops the note for the task `uuid` from the index.
+    pub fn remove(&mut self, uuid: &str) {
+        if self.data.documents.remove(uuid).is_none() {
+            return;
+        }
+        self.data.postings.retain(|_, notes| {
+            notes.remove(uuid);
+            !notes.is_empty()
+        });
+        self.changed = true;
+    }
+
+    /// Finds the notes which match `query`, as the UUIDs of their tasks and their scores, best
+    /// match first.
+    pub fn search(&self, query: &Query) -> Vec<(String, f64)> {
+        let counts = query
+            .clauses()
+            .iter()
+            .map(|clause| self.counts(clause))
+            .collect::<Vec<BTreeMap<&str, usize>>>();
+
+        let total = self.data.documents.len() as f64;
+        let mut hits = Vec::new();
+        for uuid in self.data.documents.keys() {
+            let mut score = 0.0;
+            for counts in counts.iter() {
+                let count = match counts.get(uuid.as_str()) {
+                    None => break,
+                    Some(count) => *count as f64,
+                };
+                let idf = (1.0 + total / counts.len() as f64).ln();
+                score += (1.0 + count.ln()) * idf;
+            }
+            if counts
+                .iter()
+                .all(|counts| counts.contains_key(uuid.as_str()))
+            {
+                hits.push((uuid.clone(), score));
+            }
+        }
+        hits.sort_by(|(a_uuid, a), (b_uuid, b)| {
+            b.partial_cmp(a)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a_uuid.cmp(b_uuid))
+        });
+        hits
+    }
+
+    /// Counts how many times `clause` appears in each note which contains it.
+    fn counts(&self, clause: &Clause) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        match clause {
+            Clause::Word(prefix) => {
+                let matching = self
+                    .data
+                    .postings
+                    .range(prefix.clone()..)
+                    .take_while(|(stem, _)| stem.starts_with(prefix.as_str()));
+                for (_, notes) in matching {
+                    for (uuid, positions) in notes.iter() {
+                        *counts.entry(uuid.as_str()).or_insert(0) += positions.len();
+                    }
+                }
+            }
+            Clause::Phrase(stems) => {
+                let postings = match stems
+                    .iter()
+                    .map(|stem| self.data.postings.get(stem))
+                    .collect::<Option<Vec<&BTreeMap<String, Vec<u32>>>>>()
+                {
+                    None => return counts,
+                    Some(postings) => postings,
+                };
+                for (uuid, starts) in postings[0].iter() {
+                    let count = starts
+                        .iter()
+                        .filter(|start| {
+                            postings[1..].iter().enumerate().all(|(i, notes)| {
+                                matches!(notes.get(uuid), Some(positions)
+                                    if positions.binary_search(&(*start + i as u32 + 1)).is_ok())
+                            })
+                        })
+                        .count();
+                    if count > 0 {
+                        counts.insert(uuid.as_str(), count);
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    fn save(&mut self) -> Result<()> {
+        let path = match &self.path {
+            Some(path) if matches!(path.parent(), Some(parent) if parent.exists()) => path.clone(),
+            // kept in memory only, or the root directory hasn't been created yet
+            _ => return Ok(()),
+        };
+        self.data.version = INDEX_VERSION;
+        let contents = serde_json::to_string(&self.data).map_err(|source| TasknError::Decode {
+            context: format!("the search index {}", path.display()),
+            source,
+        })?;
+        // write the index next to where it goes and then move it over, so that an interrupted
+        // write can't leave a broken index behind
+        let temporary = path.with_extension("json.tmp");
+        fs::write(&temporary, contents)
+            .and_then(|_| fs::rename(&temporary, &path))
+            .map_err(|source| TasknError::Note { path, source })?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_index_is_incremental() -> Result<()> {
+        let dir = TempDir::create("index")?;
+        let root_dir = dir.path();
+        let notes = NoteStore::new(root_dir, "md");
+        let write = |uuid: &str, contents: &str| {
+            fs::write(root_dir.join(format!("{}.md", uuid)), contents).map_err(TasknError::Io)
+        };
+        write("a", "booked the venue")?;
+        write("b", "call the caterer")?;
+
+        let query = Query::parse("booking")?;
+        let index = Index::open(&notes)?;
+        assert!(root_dir.join(INDEX_FILE).exists());
+        assert_eq!(index.search(&query).len(), 1);
+
+        // notes changed outside of taskn are picked up the next time the index is opened
+        write("b", "book the caterer, and the caterer's van")?;
+        fs::remove_file(root_dir.join("a.md")).map_err(TasknError::Io)?;
+        let index = Index::open(&notes)?;
+        let hits = index.search(&query);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, "b");
+        assert!(!index.data.postings.contains_key("venu"));
+        assert_eq!(
+            index.search(&Query::parse("\"the caterer\"")?)[0].0,
+            "b".to_string()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+//! Full-text search across notes. Notes are split into words, and each word is reduced to its
+//! stem (see [stem]), so that "booked", "booking", and "books" all match `book`. A query is a list
+//! of words and quoted phrases, and a note matches when it contains all of them, ignoring case.
+//! Words match the start of a stem (so `ven` matches "venues"), while the words of a phrase must
+//! appear exactly and in order (e.g. `"phone call"`).
+//!
+//! Matching notes are ranked with TF-IDF: words which appear more often in a note count for more,
+//! and words which appear in fewer notes count for more than common ones. The stems are kept in
+//! an [Index] in the root directory, which is brought up to date with the notes whenever it's
+//! opened.
+mod index;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::ops::Range;
+
+use crate::error::{Result, TasknError};
+use crate::notes::NoteStore;
+pub use index::Index;
+
+/// The most lines shown for each hit.
+const MAX_SNIPPETS: usize = 3;
+
+/// The most characters shown of a line around its first match.
+const SNIPPET_WIDTH: usize = 80;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    clauses: Vec<Clause>,
+}
+
+/// A part of a [Query], which every matching note must contain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Clause {
+    /// A word, as a stem which must start one of the note's stems.
+    Word(String),
+    /// Several words, as stems which must appear in the note exactly and in order.
+    Phrase(Vec<String>),
+}
+
+/// A note which matches a [Query].
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub uuid: String,
+    pub score: f64,
+    pub snippets: Vec<Snippet>,
+}
+
+/// A line of a note which matches a [Query], with the byte ranges of the matching words.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self> {
+        let mut clauses = Vec::new();
+        // every other part of the query is quoted
+        for (i, part) in query.split('"').enumerate() {
+            let stems = words(part)
+                .map(|(_, word)| stem(word))
+                .collect::<Vec<String>>();
+            if i % 2 == 1 && stems.len() > 1 {
+                clauses.push(Clause::Phrase(stems));
+            } else {
+                clauses.extend(stems.into_iter().map(Clause::Word));
+            }
+        }
+        if clauses.is_empty() {
+            return Err(TasknError::Invalid(format!(
+                "the search query '{}' doesn't contain any words",
+                query
+            )));
+        }
+        Ok(Query { clauses })
+    }
+
+    pub fn clauses(&self) -> &[Clause] {
+        &self.clauses
+    }
+
+    /// Determines which of the query's clauses `word` is part of.
+    fn matches<'a>(&'a self, word: &str) -> impl Iterator<Item = usize> + 'a {
+        let stem = stem(word);
+        self.clauses
+            .iter()
+            .enumerate()
+            .filter(move |(_, clause)| match clause {
+                Clause::Word(word) => stem.starts_with(word.as_str()),
+                Clause::Phrase(words) => words.contains(&stem),
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+impl Snippet {
+    /// Renders the snippet with `start` and `end` around each highlighted word.
+    pub fn highlight(&self, start: &str, end: &str) -> String {
+        let mut highlighted = String::with_capacity(self.text.len());
+        let mut offset = 0;
+        for range in self.highlights.iter() {
+            highlighted.push_str(&self.text[offset..range.start]);
+            highlighted.push_str(start);
+            highlighted.push_str(&self.text[range.clone()]);
+            highlighted.push_str(end);
+            offset = range.end;
+        }
+        highlighted.push_str(&self.text[offset..]);
+        highlighted
+    }
+}
+
+/// Searches every note in `notes` for `query`, best match first.
+pub fn search(notes: &NoteStore, query: &Query) -> Result<Vec<Hit>> {
+    let index = Index::open(notes)?;
+    let mut hits = Vec::new();
+    for (uuid, score) in index.search(query) {
+        let path = notes
+            .root_dir()
+            .join(&uuid)
+            .with_extension(notes.file_format());
+        let contents =
+            fs::read_to_string(&path).map_err(|source| TasknError::Note { path, source })?;
+        hits.push(Hit {
+            uuid,
+            score,
+            snippets: snippets(&contents, query),
+        });
+    }
+    Ok(hits)
+}
+
+/// Finds the UUIDs of the notes which match `query`, using an index that's already open.
+pub fn matching(index: &Index, query: &Query) -> BTreeSet<String> {
+    index
+        .search(query)
+        .into_iter()
+        .map(|(uuid, _)| uuid)
+        .collect()
+}
+
+/// Picks the lines of `contents` which match `query`, preferring lines which match more clauses.
+fn snippets(contents: &str, query: &Query) -> Vec<Snippet> {
+    let mut matching = contents
+        .lines()
+        .filter_map(|line| {
+            let mut clauses = vec![false; query.clauses.len()];
+            let mut highlights = Vec::new();
+            for (offset, word) in words(line) {
+                let mut matched = false;
+                for clause in query.matches(word) {
+                    clauses[clause] = true;
+                    matched = true;
+                }
+                if matched {
+                    highlights.push(offset..offset + word.len());
+                }
+            }
+            if highlights.is_empty() {
+                return None;
+            }
+            let matched = clauses.iter().filter(|matched| **matched).count();
+            Some((matched, trim(line, highlights)))
+        })
+        .enumerate()
+        .collect::<Vec<(usize, (usize, Snippet))>>();
+    // most clauses first, and otherwise in the order they appear in the note
+    matching.sort_by(|(a_line, (a, _)), (b_line, (b, _))| b.cmp(a).then(a_line.cmp(b_line)));
+    matching
+        .into_iter()
+        .take(MAX_SNIPPETS)
+        .map(|(_, (_, snippet))| snippet)
+        .collect()
+}
+
+/// Cuts `line` down to [SNIPPET_WIDTH] characters around its first highlight.
+fn trim(line: &str, highlights: Vec<Range<usize>>) -> Snippet {
+    let leading = line.len() - line.trim_start().len();
+    let line = line.trim();
+    let mut highlights = highlights
+        .into_iter()
+        .map(|range| range.start - leading..range.end - leading)
+        .collect::<Vec<Range<usize>>>();
+    if line.chars().count() <= SNIPPET_WIDTH {
+        return Snippet {
+            text: line.to_string(),
+            highlights,
+        };
+    }
+
+    let mut start = highlights[0].start.saturating_sub(SNIPPET_WIDTH / 4);
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let end = line[start..]
+        .char_indices()
+        .nth(SNIPPET_WIDTH)
+        .map(|(i, _)| start + i)
+        .unwrap_or(line.len());
+
+    let prefix = if start > 0 { "..." } else { "" };
+    let suffix = if end < line.len() { "..." } else { "" };
+    highlights.retain(|range| range.start >= start && range.end <= end);
+    Snippet {
+        text: format!("{}{}{}", prefix, &line[start..end], suffix),
+        highlights: highlights
+            .into_iter()
+            .map(|range| range.start - start + prefix.len()..range.end - start + prefix.len())
+            .collect(),
+    }
+}
+
+/// Splits `text` into words, along with the byte offset of each word.
+pub fn words(text: &str) -> Words<'_> {
+    Words { text, offset: 0 }
+}
+
+/// The words in a piece of text, see [words].
+pub struct Words<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> Iterator for Words<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.text[self.offset..];
+        let start = self.offset + rest.find(char::is_alphanumeric)?;
+        let len = self.text[start..]
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(self.text.len() - start);
+        self.offset = start + len;
+        Some((start, &self.text[start..start + len]))
+    }
+}
+
+/// Reduces an English word to its stem, lowercased. This is a light stemmer: it strips plurals
+/// and a few common suffixes, which is enough for "booked", "booking", and "books" to share the
+/// stem "book", without the mistakes more aggressive stemmers make on short words. Stems are
+/// only ever compared with other stems, so they don't need to be real words.
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    let mut stem = word.clone();
+    // (suffix, replacement) in order of preference, e.g. "ies" before "s"
+    let rules: [(&str, &str); 9] = [
+        ("sses", "ss"),
+        ("ies", "y"),
+        ("ied", "y"),
+        ("ss", "ss"),
+        ("us", "us"),
+        ("is", "is"),
+        ("s", ""),
+        ("ing", ""),
+        ("ed", ""),
+    ];
+    for (suffix, replacement) in rules.iter() {
+        if let Some(rest) = word.strip_suffix(suffix) {
+            if rest.chars().count() + replacement.chars().count() >= 3 {
+                stem = format!("{}{}", rest, replacement);
+            }
+            break;
+        }
+    }
+    if let Some(rest) = stem.strip_suffix("ly") {
+        if rest.chars().count() >= 3 {
+            stem = rest.to_string();
+        }
+    }
+
+    // "planned" and "running" become "plan" and "run"
+    let chars = stem.chars().collect::<Vec<char>>();
+    if let [.., a, b] = chars.as_slice() {
+        let doubled = a == b && !"aeioulsz".contains(*a) && a.is_alphabetic();
+        if doubled && word.len() > stem.len() && chars.len() > 3 {
+            stem.pop();
+        }
+    }
+    // "make" and "making" both become "mak"
+    if stem.chars().count() > 3 && stem.ends_with('e') {
+        stem.pop();
+    }
+    stem
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ranks `documents`, pairs of UUIDs and contents, against `query`. Documents which don't
+    /// match are left out, the way [search] ranks the notes on disk.
+    fn rank(documents: &[(String, String)], query: &Query) -> Vec<Hit> {
+        let mut index = Index::default();
+        for (uuid, contents) in documents.iter() {
+            index.add(uuid, contents);
+        }
+        index
+            .search(query)
+            .into_iter()
+            .filter_map(|(uuid, score)| {
+                let (_, contents) = documents.iter().find(|(other, _)| *other == uuid)?;
+                Some(Hit {
+                    snippets: snippets(contents, query),
+                    uuid,
+                    score,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rank() -> Result<()> {
+        let documents = vec![
+            (
+                "a".to_string(),
+                "Venue ideas\n\nthe venue is booked".to_string(),
+            ),
+            (
+                "b".to_string(),
+                "call about the venues, and the caterer".to_string(),
+            ),
+            ("c".to_string(), "nothing to see here".to_string()),
+        ];
+        let hits = rank(&documents, &Query::parse("venue")?);
+        assert_eq!(
+            hits.iter()
+                .map(|hit| hit.uuid.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            hits[0].snippets[1].highlight("[", "]"),
+            "the [venue] is booked"
+        );
+
+        let hits = rank(&documents, &Query::parse("THE caterer!")?);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].snippets[0].highlight("[", "]"),
+            "call about [the] venues, and [the] [caterer]"
+        );
+
+        assert!(Query::parse("?!").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stems_and_phrases() -> Result<()> {
+        for (word, expected) in [
+            ("Booked", "book"),
+            ("booking", "book"),
+            ("books", "book"),
+            ("parties", "party"),
+            ("planned", "plan"),
+            ("running", "run"),
+            ("making", "mak"),
+            ("make", "mak"),
+            ("venues", "venu"),
+            ("quickly", "quick"),
+            ("is", "is"),
+            ("the", "the"),
+            ("class", "class"),
+            ("bus", "bus"),
+        ]
+        .iter()
+        {
+            assert_eq!(stem(word), *expected, "stem of {}", word);
+        }
+
+        let documents = vec![
+            (
+                "a".to_string(),
+                "made a phone call to the venue".to_string(),
+            ),
+            (
+                "b".to_string(),
+                "call them, the phone is broken".to_string(),
+            ),
+        ];
+        let hits = rank(&documents, &Query::parse("\"phone calls\"")?);
+        assert_eq!(
+            hits.iter()
+                .map(|hit| hit.uuid.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["a"]
+        );
+        assert_eq!(
+            hits[0].snippets[0].highlight("[", "]"),
+            "made a [phone] [call] to the venue"
+        );
+        assert_eq!(rank(&documents, &Query::parse("phone calls")?).len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words("— naïve, café—ok").collect::<Vec<(usize, &str)>>(),
+            vec![(4, "naïve"), (12, "café"), (20, "ok")]
+        );
+    }
+
+    #[test]
+    fn test_trim_long_lines() {
+        let line = format!("{}needle{}", "ü".repeat(100), "x".repeat(100));
+        let highlights = vec![200..206, 300..301];
+        let snippet = trim(&line, highlights);
+        assert!(snippet.text.starts_with("...") && snippet.text.ends_with("..."));
+        // the second highlight is cut off
+        assert_eq!(snippet.highlights.len(), 1);
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "needle");
+    }
+}
@@ -0,0 +1,152 @@
+//! Taskwarrior stores tasks on disk as flat maps of string attributes, where dates are Unix
+//! timestamps and tags, dependencies, and annotations are encoded into the keys or values. This
+//! module turns such a map into the same [Task] that `task export` would have produced.
+use std::collections::BTreeMap;
+
+use chrono::{TimeZone, Utc};
+use serde_json::{Map, Value};
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{ParsableDateTime, Task};
+
+const DATE_ATTRIBUTES: &[&str] = &[
+    "entry",
+    "modified",
+    "start",
+    "end",
+    "due",
+    "scheduled",
+    "until",
+    "wait",
+];
+
+/// Attributes which Taskwarrior exports as numbers rather than strings.
+const NUMERIC_ATTRIBUTES: &[&str] = &["imask", "estimate", "taskn_order"];
+
+/// Builds a [Task] from its on-disk attributes. `id` is the task's working set ID, if it has one.
+pub fn task_from_attributes(
+    id: Option<usize>,
+    uuid: &str,
+    attributes: &BTreeMap<String, String>,
+) -> Result<Task> {
+    let mut json = Map::new();
+    let mut tags = Vec::new();
+    let mut depends = Vec::new();
+    let mut annotations = Vec::new();
+
+    for (key, value) in attributes.iter() {
+        if let Some(tag) = key.strip_prefix("tag_") {
+            tags.push(tag.to_string());
+        } else if key == "tags" {
+            tags.extend(split_list(value));
+        } else if let Some(dep) = key.strip_prefix("dep_") {
+            depends.push(dep.to_string());
+        } else if key == "depends" {
+            depends.extend(split_list(value));
+        } else if let Some(timestamp) = key.strip_prefix("annotation_") {
+            let mut annotation = Map::new();
+            annotation.insert("entry".to_string(), date(timestamp)?);
+            annotation.insert("description".to_string(), Value::String(value.clone()));
+            annotations.push(Value::Object(annotation));
+        } else if DATE_ATTRIBUTES.contains(&key.as_str()) {
+            json.insert(key.clone(), date(value)?);
+        } else if NUMERIC_ATTRIBUTES.contains(&key.as_str()) {
+            // keep integers as integers so that they can be read back as e.g. i32
+            let number = match value.parse::<i64>() {
+                Ok(integer) => Value::from(integer),
+                Err(_) => value
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .unwrap_or_else(|| Value::String(value.clone())),
+            };
+            json.insert(key.clone(), number);
+        } else {
+            json.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+
+    json.insert("id".to_string(), Value::from(id.unwrap_or(0)));
+    json.insert("uuid".to_string(), Value::String(uuid.to_string()));
+    json.entry("description".to_string())
+        .or_insert_with(|| Value::String(String::new()));
+    if !tags.is_empty() {
+        json.insert("tags".to_string(), Value::from(tags));
+    }
+    if !depends.is_empty() {
+        json.insert("depends".to_string(), Value::from(depends));
+    }
+    if !annotations.is_empty() {
+        json.insert("annotations".to_string(), Value::Array(annotations));
+    }
+
+    serde_json::from_value(Value::Object(json)).map_err(|source| TasknError::Decode {
+        context: format!("task {}", uuid),
+        source,
+    })
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .filter(|item| !item.is_empty())
+        .map(|item| item.to_string())
+}
+
+/// Converts a Unix timestamp into the date format used by `task export`.
+fn date(timestamp: &str) -> Result<Value> {
+    let seconds = timestamp.parse::<i64>().map_err(|_| {
+        TasknError::Invalid(format!("invalid timestamp '{}' in task data", timestamp))
+    })?;
+    Ok(Value::String(
+        ParsableDateTime::from(Utc.timestamp(seconds, 0)).to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_from_attributes() -> Result<()> {
+        let attributes = vec![
+            ("description", "write the report"),
+            ("status", "pending"),
+            ("entry", "1612171126"),
+            ("project", "work"),
+            ("tag_home", ""),
+            ("tag_taskn", ""),
+            ("dep_a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b", ""),
+            ("annotation_1612171200", "see the draft"),
+            ("estimate", "3"),
+            ("effort", "M"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+        let task =
+            task_from_attributes(Some(4), "2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2", &attributes)?;
+        assert_eq!(task.id, Some(4));
+        assert_eq!(task.description, "write the report");
+        assert_eq!(
+            task.entry
+                .as_ref()
+                .map(|entry| entry.to_string())
+                .as_deref(),
+            Some("20210201T091846Z")
+        );
+        assert!(task.has_tag("home") && task.has_tag("taskn"));
+        assert_eq!(task.depends.as_ref().map(|depends| depends.len()), Some(1));
+        assert_eq!(
+            task.annotations
+                .as_ref()
+                .map(|annotations| annotations.len()),
+            Some(1)
+        );
+        assert_eq!(task.estimate, Some(3));
+        assert_eq!(task.udas.get("effort"), Some(&Value::from("M")));
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::taskrc::parse_show;
+use crate::taskwarrior::Task;
+
+/// The set of operations taskn needs from Taskwarrior. Every read and write that taskn makes goes
+/// through one of these methods, so that commands can be pointed at something other than the
+/// `task` binary (e.g. the [MemoryBackend](super::MemoryBackend) in tests).
+pub trait TaskBackend {
+    /// Exports every task matching the provided Taskwarrior filter.
+    fn export(&self, filter: &[String]) -> Result<Vec<Task>>;
+
+    /// Applies modifications (e.g. `estimate:3` or `+taskn`) to the task with the provided UUID.
+    fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()>;
+
+    /// Writes each of the provided tasks back in full, replacing the stored task with the same
+    /// UUID or creating it if there is none.
+    fn import(&self, tasks: &[Task]) -> Result<()>;
+
+    /// Creates a new task and returns its UUID.
+    fn add(&self, description: &str, modifications: &[String]) -> Result<String>;
+
+    /// Sets a configuration value, as in `task config <key> <value>`.
+    fn config(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Reads the effective configuration, i.e. every setting after defaults, includes and
+    /// overrides have been applied, as in `task _show`.
+    fn show(&self) -> Result<BTreeMap<String, String>>;
+}
+
+/// Talks to Taskwarrior by spawning the `task` binary.
+#[derive(Clone)]
+pub struct SubprocessBackend {
+    program: String,
+    taskrc: Option<String>,
+    data_location: Option<String>,
+}
+
+impl SubprocessBackend {
+    pub fn new() -> Self {
+        Self {
+            program: "task".to_string(),
+            taskrc: None,
+            data_location: None,
+        }
+    }
+
+    /// Runs `task` with the provided taskrc, as if `TASKRC` were set.
+    pub fn with_taskrc(mut self, taskrc: Option<String>) -> Self {
+        self.taskrc = taskrc;
+        self
+    }
+
+    /// Runs `task` with `rc.data.location` overridden.
+    pub fn with_data_location(mut self, data_location: Option<String>) -> Self {
+        self.data_location = data_location;
+        self
+    }
+
+    /// Reads a single configuration value, as in `task _get rc.<key>`.
+    pub fn get_config(&self, key: &str) -> Result<String> {
+        let output = self.run(self.command().arg("_get").arg(format!("rc.{}", key)))?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        if let Some(taskrc) = &self.taskrc {
+            command.env("TASKRC", taskrc);
+        }
+        if let Some(data_location) = &self.data_location {
+            command.arg(format!("rc.data.location={}", data_location));
+        }
+        command
+    }
+
+    fn spawn_error(&self, source: std::io::Error) -> TasknError {
+        TasknError::Spawn {
+            program: self.program.clone(),
+            source,
+        }
+    }
+
+    fn run(&self, command: &mut Command) -> Result<Output> {
+        let output = command.output().map_err(|e| self.spawn_error(e))?;
+        self.check(output)
+    }
+
+    /// Like [SubprocessBackend::run], but feeds `stdin` to the spawned process.
+    fn run_with_stdin(&self, command: &mut Command, stdin: &[u8]) -> Result<Output> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| self.spawn_error(e))?;
+        if let Some(mut child_stdin) = child.stdin.take() {
+            child_stdin.write_all(stdin)?;
+        }
+        self.check(child.wait_with_output()?)
+    }
+
+    fn check(&self, output: Output) -> Result<Output> {
+        if !output.status.success() {
+            return Err(TasknError::Exit {
+                program: self.program.clone(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(output)
+    }
+}
+
+impl Default for SubprocessBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskBackend for SubprocessBackend {
+    fn export(&self, filter: &[String]) -> Result<Vec<Task>> {
+        let output = self.run(self.command().args(filter).arg("export"))?;
+        serde_json::from_slice(&output.stdout).map_err(|source| TasknError::Decode {
+            context: format!("the output of 'task {} export'", filter.join(" ")),
+            source,
+        })
+    }
+
+    fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()> {
+        self.run(
+            self.command()
+                .arg("rc.confirmation=off")
+                .arg(uuid)
+                .arg("modify")
+                .args(modifications),
+        )?;
+        Ok(())
+    }
+
+    fn import(&self, tasks: &[Task]) -> Result<()> {
+        if tasks.is_empty() {
+            return Ok(());
+        }
+
+        let json = serde_json::to_vec(tasks).map_err(|source| TasknError::Decode {
+            context: "tasks for 'task import'".to_string(),
+            source,
+        })?;
+        self.run_with_stdin(
+            self.command().arg("rc.confirmation=off").arg("import"),
+            &json,
+        )?;
+        Ok(())
+    }
+
+    fn add(&self, description: &str, modifications: &[String]) -> Result<String> {
+        let output = self.run(
+            self.command()
+                .arg("rc.verbose=new-uuid")
+                .arg("add")
+                .arg(description)
+                .args(modifications),
+        )?;
+
+        // with rc.verbose=new-uuid taskwarrior prints "Created task <uuid>."
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        stdout
+            .split_whitespace()
+            .last()
+            .map(|word| word.trim_end_matches('.').to_string())
+            .ok_or_else(|| {
+                TasknError::Invalid(format!(
+                    "taskwarrior did not report the UUID of new task '{}'",
+                    description
+                ))
+            })
+    }
+
+    fn config(&self, key: &str, value: &str) -> Result<()> {
+        self.run(
+            self.command()
+                .arg("rc.confirmation=off")
+                .arg("config")
+                .arg(key)
+                .arg(value),
+        )?;
+        Ok(())
+    }
+
+    fn show(&self) -> Result<BTreeMap<String, String>> {
+        let output = self.run(self.command().arg("_show"))?;
+        Ok(parse_show(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
@@ -0,0 +1,249 @@
+//! Reads tasks straight out of Taskwarrior 2.x's `pending.data` and `completed.data`, which store
+//! one task per line as `[key:"value" key:"value" ...]`. Like the TaskChampion backend, this only
+//! covers reads; writes still go through the `task` binary.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::attributes::task_from_attributes;
+use crate::taskwarrior::backend::{SubprocessBackend, TaskBackend};
+use crate::taskwarrior::filter::Filter;
+use crate::taskwarrior::Task;
+
+pub struct DataFileBackend {
+    pending: PathBuf,
+    completed: PathBuf,
+    fallback: SubprocessBackend,
+}
+
+impl DataFileBackend {
+    /// Opens the data files inside of `data_location`, returning `None` if there aren't any.
+    /// `fallback` is used for writes, and for reads which taskn can't handle itself.
+    pub fn open(data_location: &Path, fallback: SubprocessBackend) -> Option<Self> {
+        let pending = data_location.join("pending.data");
+        if !pending.is_file() {
+            return None;
+        }
+
+        Some(Self {
+            pending,
+            completed: data_location.join("completed.data"),
+            fallback,
+        })
+    }
+
+    /// Reads every task in one data file. Returns `None` if any line isn't in the format we
+    /// expect, or holds a task we can't make sense of, in which case the caller should defer to
+    /// Taskwarrior.
+    fn read_tasks(&self, path: &Path, has_ids: bool) -> Result<Option<Vec<Task>>> {
+        let contents = match fs::read_to_string(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Some(Vec::new())),
+            Err(e) => {
+                return Err(TasknError::Store {
+                    path: path.to_path_buf(),
+                    message: e.to_string(),
+                })
+            }
+            Ok(contents) => contents,
+        };
+
+        let mut next_id = 1;
+        let mut tasks = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let attributes = match parse_line(line) {
+                None => return Ok(None),
+                Some(attributes) => attributes,
+            };
+            let uuid = match attributes.get("uuid") {
+                None => return Ok(None),
+                Some(uuid) => uuid.clone(),
+            };
+
+            // mirrors Taskwarrior, which numbers everything in pending.data that hasn't been
+            // completed or deleted (and is therefore waiting to be garbage collected)
+            let status = attributes.get("status").map(|s| s.as_str());
+            let id = if has_ids && status != Some("completed") && status != Some("deleted") {
+                next_id += 1;
+                Some(next_id - 1)
+            } else {
+                None
+            };
+            match task_from_attributes(id, &uuid, &attributes) {
+                Err(_) => return Ok(None),
+                Ok(task) => tasks.push(task),
+            }
+        }
+        Ok(Some(tasks))
+    }
+}
+
+impl TaskBackend for DataFileBackend {
+    fn export(&self, filter: &[String]) -> Result<Vec<Task>> {
+        let parsed = match Filter::parse(filter) {
+            None => return self.fallback.export(filter),
+            Some(parsed) => parsed,
+        };
+
+        let mut tasks = match self.read_tasks(&self.pending, true)? {
+            None => return self.fallback.export(filter),
+            Some(tasks) => tasks,
+        };
+        if !parsed.selects_only_pending() {
+            match self.read_tasks(&self.completed, false)? {
+                None => return self.fallback.export(filter),
+                Some(completed) => tasks.extend(completed),
+            }
+        }
+
+        tasks.retain(|task| parsed.matches(task));
+        Ok(tasks)
+    }
+
+    fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()> {
+        self.fallback.modify(uuid, modifications)
+    }
+
+    fn import(&self, tasks: &[Task]) -> Result<()> {
+        self.fallback.import(tasks)
+    }
+
+    fn add(&self, description: &str, modifications: &[String]) -> Result<String> {
+        self.fallback.add(description, modifications)
+    }
+
+    fn config(&self, key: &str, value: &str) -> Result<()> {
+        self.fallback.config(key, value)
+    }
+
+    fn show(&self) -> Result<BTreeMap<String, String>> {
+        self.fallback.show()
+    }
+}
+
+/// Parses a single `[key:"value" ...]` line. Values are JSON-escaped, and brackets and quotes
+/// inside of them are additionally written as `&open;`, `&close;`, and `&dquot;`.
+fn parse_line(line: &str) -> Option<BTreeMap<String, String>> {
+    let body = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let mut chars = body.chars().peekable();
+    let mut attributes = BTreeMap::new();
+
+    loop {
+        while chars.peek() == Some(&' ') {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        loop {
+            match chars.next()? {
+                ':' => break,
+                c if c.is_whitespace() || c == '"' => return None,
+                c => key.push(c),
+            }
+        }
+        if key.is_empty() || chars.next()? != '"' {
+            return None;
+        }
+
+        let mut value = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    '/' => value.push('/'),
+                    'b' => value.push('\u{8}'),
+                    'f' => value.push('\u{c}'),
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    'u' => {
+                        let code: String = (0..4).filter_map(|_| chars.next()).collect();
+                        value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                    }
+                    _ => return None,
+                },
+                c => value.push(c),
+            }
+        }
+
+        let value = value
+            .replace("&open;", "[")
+            .replace("&close;", "]")
+            .replace("&dquot;", "\"");
+        attributes.insert(key, value);
+    }
+
+    Some(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_parse_line() {
+        let attributes = parse_line(
+            r#"[description:"read &open;the&close; \"book\"" entry:"1612171126" status:"pending" tags:"home,taskn" uuid:"2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2"]"#,
+        )
+        .unwrap();
+        assert_eq!(attributes["description"], r#"read [the] "book""#);
+        assert_eq!(attributes["tags"], "home,taskn");
+    }
+
+    #[test]
+    fn test_parse_line_unfamiliar() {
+        assert_eq!(parse_line(r#"description:"no brackets""#), None);
+        assert_eq!(parse_line(r#"[description:"unterminated]"#), None);
+        assert_eq!(parse_line(r#"[description "missing colon"]"#), None);
+    }
+
+    #[test]
+    fn test_export_assigns_ids() -> Result<()> {
+        let dir = TempDir::create("data")?;
+        let data_location = dir.path();
+        fs::write(
+            data_location.join("pending.data"),
+            concat!(
+                r#"[description:"first" entry:"1612171126" status:"pending" uuid:"2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2"]"#,
+                "\n",
+                r#"[description:"finished" end:"1612200000" entry:"1612171126" status:"completed" uuid:"a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b"]"#,
+                "\n",
+                r#"[description:"second" entry:"1612171126" status:"pending" uuid:"b7c8d9e0-1f2a-4b3c-8d4e-5f6a7b8c9d0e"]"#,
+                "\n",
+            ),
+        )?;
+
+        let backend = DataFileBackend::open(data_location, SubprocessBackend::new()).unwrap();
+        let tasks = backend.export(&["status:pending".to_string()])?;
+
+        let ids = tasks
+            .iter()
+            .map(|task| (task.description.as_str(), task.id))
+            .collect::<Vec<(&str, Option<usize>)>>();
+        assert_eq!(ids, vec![("first", Some(1)), ("second", Some(2))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tasks_defers_undecodable() -> Result<()> {
+        let dir = TempDir::create("data-undecodable")?;
+        fs::write(
+            dir.path().join("pending.data"),
+            concat!(
+                r#"[description:"first" due:"soon" entry:"1612171126" status:"pending" uuid:"2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2"]"#,
+                "\n",
+            ),
+        )?;
+
+        let backend = DataFileBackend::open(dir.path(), SubprocessBackend::new()).unwrap();
+        assert!(backend.read_tasks(&backend.pending, true)?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! A small evaluator for the subset of Taskwarrior's filter language that taskn itself uses.
+//! Backends which don't delegate filtering to the `task` binary use this to select tasks.
+//! Anything outside of this subset fails to parse, so that callers can refuse (or fall back to
+//! Taskwarrior) rather than silently selecting the wrong tasks.
+use crate::taskwarrior::Task;
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Term(Term),
+}
+
+#[derive(Debug, PartialEq)]
+enum Term {
+    Ids(Vec<(usize, usize)>),
+    Uuid(String),
+    HasTag(String),
+    LacksTag(String),
+    Attribute(String, String),
+    Word(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parses a filter out of Taskwarrior command line arguments. Returns `None` if the filter
+    /// uses syntax that taskn does not understand.
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Option<Filter> {
+        let mut tokens = Vec::new();
+        for arg in args.iter() {
+            let arg = arg.as_ref().replace('(', " ( ").replace(')', " ) ");
+            tokens.extend(arg.split_whitespace().map(|s| s.to_string()));
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if parser.pos != parser.tokens.len() {
+            return None;
+        }
+        Some(Filter { expr })
+    }
+
+    /// Determines whether or not the provided [Task] is selected by this filter.
+    pub fn matches(&self, task: &Task) -> bool {
+        self.expr.matches(task)
+    }
+
+    /// Determines whether this filter can only select pending or waiting tasks, i.e. whether it
+    /// requires one of `status:pending` or `status:waiting`. Backends use this to skip reading
+    /// completed tasks when they can't be selected anyway.
+    pub fn selects_only_pending(&self) -> bool {
+        self.expr.selects_only_pending()
+    }
+}
+
+/// Determines whether a command line argument looks like part of a Taskwarrior filter (an ID,
+/// UUID, tag, attribute, operator, ...) rather than a plain word.
+pub fn looks_like_filter(arg: &str) -> bool {
+    match parse_term(arg) {
+        Some(Term::Word(word)) => matches!(word.as_str(), "(" | ")" | "and" | "or"),
+        Some(_) => true,
+        // anything we can't parse ourselves uses syntax that plain words don't
+        None => true,
+    }
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|s| s.as_str())
+    }
+
+    fn parse_or(&mut self) -> Option<Expr> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            exprs.push(self.parse_and()?);
+        }
+        Some(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> Option<Expr> {
+        let mut exprs = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(")") | Some("or") => break,
+                Some("and") => self.pos += 1,
+                Some("(") => {
+                    self.pos += 1;
+                    exprs.push(self.parse_or()?);
+                    if self.peek() != Some(")") {
+                        return None;
+                    }
+                    self.pos += 1;
+                }
+                Some(token) => {
+                    let term = parse_term(token)?;
+                    self.pos += 1;
+                    exprs.push(Expr::Term(term));
+                }
+            }
+        }
+        Some(Expr::And(exprs))
+    }
+}
+
+fn parse_term(token: &str) -> Option<Term> {
+    if token == "xor" || token == "not" {
+        return None;
+    }
+
+    if let Some(tag) = token.strip_prefix('+') {
+        return parse_tag(tag).map(Term::HasTag);
+    }
+    if let Some(tag) = token.strip_prefix('-') {
+        return parse_tag(tag).map(Term::LacksTag);
+    }
+
+    if let Some(colon) = token.find(':') {
+        let (key, value) = (&token[..colon], &token[colon + 1..]);
+        // attribute modifiers (e.g. due.before:) and date math are out of scope
+        if key.is_empty() || key.contains('.') || (is_date_attribute(key) && !value.is_empty()) {
+            return None;
+        }
+        return Some(Term::Attribute(key.to_string(), value.to_string()));
+    }
+
+    if let Some(ids) = parse_ids(token) {
+        return Some(Term::Ids(ids));
+    }
+    if is_uuid_prefix(token) {
+        return Some(Term::Uuid(token.to_lowercase()));
+    }
+
+    if token.contains(|c: char| "<>=!~^$*".contains(c)) {
+        return None;
+    }
+    Some(Term::Word(token.to_string()))
+}
+
+fn parse_tag(tag: &str) -> Option<String> {
+    // uppercase tags are Taskwarrior's virtual tags (+PENDING, +OVERDUE, ...)
+    if tag.is_empty() || tag.chars().all(|c| !c.is_lowercase()) {
+        None
+    } else {
+        Some(tag.to_string())
+    }
+}
+
+fn parse_ids(token: &str) -> Option<Vec<(usize, usize)>> {
+    let mut ids = Vec::new();
+    for part in token.split(',') {
+        let range = match part.find('-') {
+            Some(dash) => (part[..dash].parse().ok()?, part[dash + 1..].parse().ok()?),
+            None => {
+                let id = part.parse().ok()?;
+                (id, id)
+            }
+        };
+        ids.push(range);
+    }
+    Some(ids)
+}
+
+fn is_uuid_prefix(token: &str) -> bool {
+    token.len() >= 8
+        && token.len() <= 36
+        && token.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+        && token.chars().take(8).all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_date_attribute(key: &str) -> bool {
+    matches!(
+        key,
+        "entry" | "modified" | "start" | "end" | "due" | "scheduled" | "until" | "wait"
+    )
+}
+
+fn attribute(task: &Task, key: &str) -> Option<String> {
+    match key {
+        "description" => Some(task.description.clone()),
+        "status" => Some(task.status.clone()),
+        "uuid" => Some(task.uuid.clone()),
+        "project" => task.project.clone(),
+        "priority" => task.priority.clone(),
+        "recur" => task.recur.clone(),
+        "parent" => task.parent.clone(),
+        "estimate" => task.estimate.map(|estimate| estimate.to_string()),
+        "taskn_order" => task.taskn_order.map(|order| order.to_string()),
+        "taskn_reminder_uuid" => task.taskn_reminder_uuid.clone(),
+        "entry" => task.entry.as_ref().map(|_| String::new()),
+        "modified" => task.modified.as_ref().map(|_| String::new()),
+        "start" => task.start.as_ref().map(|_| String::new()),
+        "end" => task.end.as_ref().map(|_| String::new()),
+        "due" => task.due.as_ref().map(|_| String::new()),
+        "scheduled" => task.scheduled.as_ref().map(|_| String::new()),
+        "until" => task.until.as_ref().map(|_| String::new()),
+        "wait" => task.wait.as_ref().map(|_| String::new()),
+        _ => task.udas.get(key).map(|value| match value {
+            serde_json::Value::String(s) => s.clone(),
+            value => value.to_string(),
+        }),
+    }
+}
+
+impl Expr {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(task)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(task)),
+            Expr::Term(term) => term.matches(task),
+        }
+    }
+
+    fn selects_only_pending(&self) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().any(|expr| expr.selects_only_pending()),
+            Expr::Or(exprs) => exprs.iter().all(|expr| expr.selects_only_pending()),
+            Expr::Term(Term::Attribute(key, value)) => {
+                key == "status" && (value == "pending" || value == "waiting")
+            }
+            Expr::Term(_) => false,
+        }
+    }
+}
+
+impl Term {
+    fn matches(&self, task: &Task) -> bool {
+        match self {
+            Term::Ids(ids) => match task.id {
+                None => false,
+                Some(id) => ids.iter().any(|(low, high)| *low <= id && id <= *high),
+            },
+            Term::Uuid(prefix) => task.uuid.to_lowercase().starts_with(prefix),
+            Term::HasTag(tag) => task.has_tag(tag),
+            Term::LacksTag(tag) => !task.has_tag(tag),
+            Term::Attribute(key, value) => match (attribute(task, key), key.as_str()) {
+                (None, _) => value.is_empty(),
+                (Some(_), _) if value.is_empty() => false,
+                (Some(actual), "description") => actual.contains(value.as_str()),
+                // project:foo also selects its subprojects (e.g. foo.bar)
+                (Some(actual), "project") => {
+                    actual == *value || actual.starts_with(&format!("{}.", value))
+                }
+                (Some(actual), _) => actual == *value,
+            },
+            Term::Word(word) => task.description.contains(word.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_common_filters() {
+        assert!(Filter::parse(&["status:pending"]).is_some());
+        assert!(Filter::parse(&["+remindme", "(status:pending or status:waiting)"]).is_some());
+        assert!(Filter::parse(&["1-3,7"]).is_some());
+        assert!(Filter::parse::<&str>(&[]).is_some());
+    }
+
+    #[test]
+    fn test_parse_unsupported_filters() {
+        assert_eq!(Filter::parse(&["due.before:today"]), None);
+        assert_eq!(Filter::parse(&["+OVERDUE"]), None);
+        assert_eq!(Filter::parse(&["(status:pending"]), None);
+        assert_eq!(Filter::parse(&["/regex/~"]), None);
+    }
+
+    #[test]
+    fn test_looks_like_filter() {
+        for arg in [
+            "16",
+            "1-3,7",
+            "+home",
+            "project:work",
+            "2f3a4c7e",
+            "due.before:today",
+        ]
+        .iter()
+        {
+            assert!(looks_like_filter(arg), "{}", arg);
+        }
+        assert!(!looks_like_filter("ordr"));
+    }
+
+    #[test]
+    fn test_selects_only_pending() {
+        let selects_only_pending =
+            |args: &[&str]| Filter::parse(args).unwrap().selects_only_pending();
+        assert!(selects_only_pending(&["status:pending", "+home"]));
+        assert!(selects_only_pending(&[
+            "(status:pending or status:waiting)"
+        ]));
+        assert!(!selects_only_pending(&["status:pending or +home"]));
+        assert!(!selects_only_pending(&["+home"]));
+    }
+}
@@ -0,0 +1,272 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::backend::TaskBackend;
+use crate::taskwarrior::filter::Filter;
+use crate::taskwarrior::{ParsableDateTime, Task};
+
+/// An in-memory stand-in for Taskwarrior. Commands can be run against a [MemoryBackend] seeded
+/// with a handful of tasks, after which the resulting tasks and configuration can be inspected.
+#[derive(Default)]
+pub struct MemoryBackend {
+    tasks: RefCell<Vec<Task>>,
+    config: RefCell<BTreeMap<String, String>>,
+}
+
+impl MemoryBackend {
+    pub fn new(tasks: Vec<Task>) -> Self {
+        Self {
+            tasks: RefCell::new(tasks),
+            config: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns a snapshot of every task currently stored, in insertion order.
+    pub fn tasks(&self) -> Vec<Task> {
+        self.tasks.borrow().clone()
+    }
+
+    /// Returns the task with the provided UUID, if it exists.
+    pub fn task(&self, uuid: &str) -> Option<Task> {
+        self.tasks
+            .borrow()
+            .iter()
+            .find(|task| task.uuid == uuid)
+            .cloned()
+    }
+
+    /// Returns the configuration value stored under `key` through [TaskBackend::config].
+    pub fn config_value(&self, key: &str) -> Option<String> {
+        self.config.borrow().get(key).cloned()
+    }
+}
+
+impl TaskBackend for MemoryBackend {
+    fn export(&self, filter: &[String]) -> Result<Vec<Task>> {
+        let filter = Filter::parse(filter).ok_or_else(|| {
+            TasknError::Invalid(format!("unsupported filter '{}'", filter.join(" ")))
+        })?;
+        Ok(self
+            .tasks
+            .borrow()
+            .iter()
+            .filter(|task| filter.matches(task))
+            .cloned()
+            .collect())
+    }
+
+    fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()> {
+        let mut tasks = self.tasks.borrow_mut();
+        let task = tasks
+            .iter_mut()
+            .find(|task| task.uuid == uuid)
+            .ok_or_else(|| TasknError::Invalid(format!("no task '{}'", uuid)))?;
+        apply_modifications(task, modifications)
+    }
+
+    fn import(&self, tasks: &[Task]) -> Result<()> {
+        let mut stored = self.tasks.borrow_mut();
+        for task in tasks.iter() {
+            match stored.iter_mut().find(|stored| stored.uuid == task.uuid) {
+                Some(stored) => *stored = task.clone(),
+                None => stored.push(task.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&self, description: &str, modifications: &[String]) -> Result<String> {
+        let mut tasks = self.tasks.borrow_mut();
+        let id = tasks.iter().filter_map(|task| task.id).max().unwrap_or(0) + 1;
+        let mut task = Task {
+            id: Some(id),
+            description: description.to_string(),
+            uuid: format!("00000000-0000-4000-8000-{:012x}", tasks.len() + 1),
+            status: "pending".to_string(),
+            ..Task::default()
+        };
+        apply_modifications(&mut task, modifications)?;
+
+        let uuid = task.uuid.clone();
+        tasks.push(task);
+        Ok(uuid)
+    }
+
+    fn config(&self, key: &str, value: &str) -> Result<()> {
+        self.config
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn show(&self) -> Result<BTreeMap<String, String>> {
+        Ok(self.config.borrow().clone())
+    }
+}
+
+/// Applies Taskwarrior-style modification arguments to a [Task]: `+tag`, `-tag`, `key:value`,
+/// `key:` to clear an attribute, and free text which replaces the description.
+fn apply_modifications(task: &mut Task, modifications: &[String]) -> Result<()> {
+    let mut description = Vec::new();
+    for modification in modifications.iter() {
+        if let Some(tag) = modification.strip_prefix('+') {
+            let tags = task.tags.get_or_insert_with(Vec::new);
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        } else if let Some(tag) = modification.strip_prefix('-') {
+            if let Some(tags) = &mut task.tags {
+                tags.retain(|t| t != tag);
+                if tags.is_empty() {
+                    task.tags = None;
+                }
+            }
+        } else if let Some(colon) = modification.find(':') {
+            let (key, value) = (&modification[..colon], &modification[colon + 1..]);
+            set_attribute(task, key, value)?;
+        } else {
+            description.push(modification.as_str());
+        }
+    }
+
+    if !description.is_empty() {
+        task.description = description.join(" ");
+    }
+    Ok(())
+}
+
+fn set_attribute(task: &mut Task, key: &str, value: &str) -> Result<()> {
+    let invalid = || TasknError::Invalid(format!("invalid value '{}' for '{}'", value, key));
+    let optional = |value: &str| {
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    };
+    let date = |value: &str| match value {
+        "" => Ok(None),
+        value => value
+            .parse::<ParsableDateTime>()
+            .map(Some)
+            .map_err(|_| invalid()),
+    };
+
+    match key {
+        "description" => task.description = value.to_string(),
+        "status" => task.status = value.to_string(),
+        "project" => task.project = optional(value),
+        "priority" => task.priority = optional(value),
+        "estimate" => {
+            task.estimate = match optional(value) {
+                None => None,
+                Some(value) => Some(value.parse().map_err(|_| invalid())?),
+            }
+        }
+        "taskn_order" => {
+            task.taskn_order = match optional(value) {
+                None => None,
+                Some(value) => Some(value.parse().map_err(|_| invalid())?),
+            }
+        }
+        "taskn_reminder_uuid" => task.taskn_reminder_uuid = optional(value),
+        "entry" => task.entry = date(value)?,
+        "modified" => task.modified = date(value)?,
+        "start" => task.start = date(value)?,
+        "end" => task.end = date(value)?,
+        "due" => task.due = date(value)?,
+        "scheduled" => task.scheduled = date(value)?,
+        "until" => task.until = date(value)?,
+        "wait" => task.wait = date(value)?,
+        "depends" | "recur" | "parent" | "mask" | "imask" | "urgency" | "uuid" => {
+            return Err(TasknError::Invalid(format!(
+                "unsupported attribute '{}'",
+                key
+            )))
+        }
+        _ => match optional(value) {
+            None => {
+                task.udas.remove(key);
+            }
+            Some(value) => {
+                task.udas
+                    .insert(key.to_string(), serde_json::Value::String(value));
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::task;
+
+    #[test]
+    fn test_export_filters_tasks() -> Result<()> {
+        let mut done = task(2).with_description("done task");
+        done.status = "completed".to_string();
+        let backend = MemoryBackend::new(vec![task(1).with_description("pending task"), done]);
+
+        let tasks = backend.export(&["status:pending".to_string()])?;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].description, "pending task");
+        Ok(())
+    }
+
+    #[test]
+    fn test_modify_applies_modifications() -> Result<()> {
+        let backend = MemoryBackend::new(vec![task(1).with_description("a task")]);
+        let uuid = backend.tasks()[0].uuid.clone();
+
+        backend.modify(
+            &uuid,
+            &[
+                "+taskn".to_string(),
+                "estimate:3".to_string(),
+                "a new description".to_string(),
+            ],
+        )?;
+
+        let task = backend.task(&uuid).unwrap();
+        assert!(task.has_tag("taskn"));
+        assert_eq!(task.estimate, Some(3));
+        assert_eq!(task.description, "a new description");
+
+        backend.modify(&uuid, &["-taskn".to_string(), "estimate:".to_string()])?;
+        let task = backend.task(&uuid).unwrap();
+        assert!(!task.has_tag("taskn"));
+        assert_eq!(task.estimate, None);
+
+        backend.modify(&uuid, &["wait:20210501T120000Z".to_string()])?;
+        let task = backend.task(&uuid).unwrap();
+        assert_eq!(
+            task.wait.map(|wait| wait.to_string()).as_deref(),
+            Some("20210501T120000Z")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_assigns_uuid() -> Result<()> {
+        let backend = MemoryBackend::default();
+        let uuid = backend.add("new task", &["+home".to_string()])?;
+
+        let task = backend.task(&uuid).unwrap();
+        assert_eq!(task.id, Some(1));
+        assert!(task.has_tag("home"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_is_recorded() -> Result<()> {
+        let backend = MemoryBackend::default();
+        backend.config("uda.estimate.type", "numeric")?;
+        assert_eq!(
+            backend.config_value("uda.estimate.type"),
+            Some("numeric".to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,562 @@
+mod attributes;
+mod backend;
+mod datafile;
+pub mod filter;
+mod memory;
+#[cfg(feature = "taskchampion")]
+mod taskchampion;
+mod taskrc;
+mod transaction;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::slice;
+use std::str::{self, FromStr};
+
+use chrono::offset::Local;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::de;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use shellexpand::tilde;
+use uuid::Uuid;
+
+use crate::error::{self, TasknError};
+pub use backend::{SubprocessBackend, TaskBackend};
+pub use datafile::DataFileBackend;
+pub use memory::MemoryBackend;
+#[cfg(feature = "taskchampion")]
+pub use taskchampion::TaskChampionBackend;
+pub use taskrc::Taskrc;
+pub use transaction::{PlannedChange, Transaction};
+
+/// Picks the fastest way to talk to the user's Taskwarrior. Reads go directly to Taskwarrior's
+/// data files when taskn understands them, and everything else goes through the `task` binary.
+/// `taskrc` and `data_location` override Taskwarrior's defaults, as with `TASKRC` and
+/// `rc.data.location`.
+pub fn open_backend(
+    taskrc: Option<String>,
+    data_location: Option<String>,
+) -> error::Result<Box<dyn TaskBackend>> {
+    let subprocess = SubprocessBackend::new()
+        .with_taskrc(taskrc)
+        .with_data_location(data_location.clone());
+    let data_location = match data_location {
+        Some(data_location) => PathBuf::from(data_location),
+        None => PathBuf::from(tilde(&subprocess.get_config("data.location")?).as_ref()),
+    };
+
+    #[cfg(feature = "taskchampion")]
+    {
+        if let Some(backend) = TaskChampionBackend::open(&data_location, subprocess.clone())? {
+            return Ok(Box::new(backend));
+        }
+    }
+
+    if let Some(backend) = DataFileBackend::open(&data_location, subprocess.clone()) {
+        return Ok(Box::new(backend));
+    }
+
+    Ok(Box::new(subprocess))
+}
+
+/// A single task, as exported by `task export`.
+///
+/// Every attribute that Taskwarrior itself defines is modeled explicitly. Any other attributes
+/// (i.e. UDAs that taskn doesn't use) are kept in [Task::udas] so that they survive a
+/// read-modify-write cycle.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Task {
+    /// The working set ID of the task. Completed and deleted tasks aren't part of the working set,
+    /// and Taskwarrior exports them with an ID of 0, so they have no ID here.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<usize>,
+    pub description: String,
+    pub uuid: String,
+    pub status: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<ParsableDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wait: Option<ParsableDateTime>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_depends",
+        serialize_with = "serialize_depends",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub depends: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
+    /// Urgency is computed by Taskwarrior, so it is never written back.
+    #[serde(skip_serializing)]
+    pub urgency: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recur: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imask: Option<f64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<i32>,
+    /// taskn's rank for the task, see [crate::ordering].
+    #[serde(
+        default,
+        serialize_with = "serialize_rank",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub taskn_order: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub taskn_reminder_uuid: Option<String>,
+
+    /// Every other attribute on the task, keyed by name.
+    #[serde(flatten)]
+    pub udas: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Annotation {
+    pub entry: ParsableDateTime,
+    pub description: String,
+}
+
+impl Task {
+    /// A new pending task with a fresh UUID, which isn't in Taskwarrior until it's imported.
+    /// Unlike `task add`, this never parses attributes (e.g. `project:home`) out of the
+    /// description.
+    pub fn new(description: &str) -> Self {
+        Task {
+            description: description.to_string(),
+            uuid: Uuid::new_v4().to_string(),
+            status: "pending".to_string(),
+            entry: Some(ParsableDateTime::from(Local::now())),
+            ..Task::default()
+        }
+    }
+
+    /// Saves anything stored inside this Task to taskwarrior.
+    pub fn save(&self, backend: &dyn TaskBackend) -> error::Result<()> {
+        backend.import(slice::from_ref(self))
+    }
+
+    pub fn get<'a, S: ToString, I: Iterator<Item = S>>(
+        backend: &dyn TaskBackend,
+        taskwarrior_args: I,
+    ) -> error::Result<Vec<Self>> {
+        let taskwarrior_args = taskwarrior_args
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        backend.export(&taskwarrior_args)
+    }
+
+    /// Exports the tasks with the provided UUIDs, whatever their status, keyed by UUID. UUIDs
+    /// which don't match any task are left out.
+    pub fn get_by_uuid<S: AsRef<str>>(
+        backend: &dyn TaskBackend,
+        uuids: &[S],
+    ) -> error::Result<BTreeMap<String, Self>> {
+        if uuids.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let mut filter = Vec::new();
+        for uuid in uuids.iter() {
+            if !filter.is_empty() {
+                filter.push("or".to_string());
+            }
+            filter.push(uuid.as_ref().to_string());
+        }
+        Ok(backend
+            .export(&filter)?
+            .into_iter()
+            .map(|task| (task.uuid.clone(), task))
+            .collect())
+    }
+
+    /// The task's attributes as they'd be imported into Taskwarrior, without its ID (which
+    /// Taskwarrior may renumber at any time).
+    pub fn attributes(&self) -> error::Result<Map<String, Value>> {
+        let value = serde_json::to_value(self).map_err(|source| TasknError::Decode {
+            context: format!("task {}", self.uuid),
+            source,
+        })?;
+        let mut attributes = match value {
+            Value::Object(attributes) => attributes,
+            _ => Map::new(),
+        };
+        attributes.remove("id");
+        Ok(attributes)
+    }
+
+    /// Lists the attributes which differ between this task and `other`, by name.
+    pub fn diff(&self, other: &Task) -> error::Result<Vec<Difference>> {
+        let before = self.attributes()?;
+        let after = other.attributes()?;
+        let mut names = before
+            .keys()
+            .chain(after.keys())
+            .filter(|name| before.get(*name) != after.get(*name))
+            .cloned()
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        Ok(names
+            .into_iter()
+            .map(|name| Difference {
+                before: before.get(&name).cloned(),
+                after: after.get(&name).cloned(),
+                name,
+            })
+            .collect())
+    }
+
+    pub fn set_estimate(
+        &mut self,
+        backend: &dyn TaskBackend,
+        estimate: Option<i32>,
+    ) -> error::Result<()> {
+        self.estimate = estimate;
+        self.save(backend)
+    }
+
+    /// The task's rank in the ordering kept in the UDA `uda`, see [crate::ordering].
+    pub fn rank(&self, uda: &str) -> Option<f64> {
+        if uda == "taskn_order" {
+            return self.taskn_order;
+        }
+        match self.udas.get(uda)? {
+            Value::Number(number) => number.as_f64(),
+            // UDAs which taskn doesn't model are read from Taskwarrior's data files as strings
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Sets the task's rank in the ordering kept in the UDA `uda`, see [crate::ordering].
+    pub fn set_rank(&mut self, uda: &str, rank: Option<f64>) {
+        if uda == "taskn_order" {
+            self.taskn_order = rank;
+            return;
+        }
+        match rank {
+            None => {
+                self.udas.remove(uda);
+            }
+            // whole ranks are written as integers, like in [serialize_rank]
+            Some(rank) if rank.fract() == 0.0 && rank.abs() < i64::MAX as f64 => {
+                self.udas.insert(uda.to_string(), Value::from(rank as i64));
+            }
+            Some(rank) => {
+                self.udas.insert(uda.to_string(), Value::from(rank));
+            }
+        }
+    }
+
+    /// Determines whether or not the [Task] contains a tag with the provided value.
+    pub fn has_tag<S: AsRef<str>>(&self, s: S) -> bool {
+        match &self.tags {
+            None => false,
+            Some(tags) => {
+                let s = s.as_ref();
+                for tag in tags.into_iter() {
+                    if tag == s {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    pub fn set_reminder_uuid(
+        &mut self,
+        backend: &dyn TaskBackend,
+        uuid: String,
+    ) -> error::Result<()> {
+        self.taskn_reminder_uuid = Some(uuid);
+        self.save(backend)
+    }
+}
+
+/// A single attribute which differs between two copies of a task. A value of `None` means the
+/// attribute isn't set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    pub name: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            None => "(none)".to_string(),
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+        };
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name,
+            show(&self.before),
+            show(&self.after)
+        )
+    }
+}
+
+fn deserialize_id<'de, D: de::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    let id = usize::deserialize(deserializer)?;
+    Ok(if id == 0 { None } else { Some(id) })
+}
+
+/// Taskwarrior 2.5 exports `depends` as a comma-separated string of UUIDs, whereas later versions
+/// export a list. Either way we store a list.
+fn deserialize_depends<'de, D: de::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Depends {
+        List(Vec<String>),
+        Joined(String),
+    }
+
+    Ok(match Option::<Depends>::deserialize(deserializer)? {
+        None => None,
+        Some(Depends::List(uuids)) => Some(uuids),
+        Some(Depends::Joined(uuids)) => Some(
+            uuids
+                .split(',')
+                .filter(|uuid| !uuid.is_empty())
+                .map(|uuid| uuid.to_string())
+                .collect(),
+        ),
+    })
+}
+
+/// Writes `depends` in the comma-separated form, which every version of `task import` accepts.
+fn serialize_depends<S: Serializer>(
+    depends: &Option<Vec<String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match depends {
+        None => serializer.serialize_none(),
+        Some(uuids) => serializer.serialize_str(&uuids.join(",")),
+    }
+}
+
+/// Writes whole ranks as integers (`3` rather than `3.0`), the same way Taskwarrior prints them.
+fn serialize_rank<S: Serializer>(rank: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match rank {
+        None => serializer.serialize_none(),
+        Some(rank) if rank.fract() == 0.0 && rank.abs() < i64::MAX as f64 => {
+            serializer.serialize_i64(*rank as i64)
+        }
+        Some(rank) => serializer.serialize_f64(*rank),
+    }
+}
+
+/// The format Taskwarrior uses for dates in its JSON import and export. These dates are always in
+/// UTC, regardless of the user's timezone.
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct ParsableDateTime(pub DateTime<Local>);
+
+impl<Tz: TimeZone> From<DateTime<Tz>> for ParsableDateTime {
+    fn from(date_time: DateTime<Tz>) -> Self {
+        ParsableDateTime(date_time.with_timezone(&Local))
+    }
+}
+
+impl FromStr for ParsableDateTime {
+    type Err = chrono::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // this is a little cursed, but for good reason
+        // chrono isn't happy parsing a DateTime without an associated timezone
+        // so we parse a DateTime first
+        // and then we know it's always in UTC so we make a DateTime<Local> from it
+        // and finally convert that back into the DateTime, which is what we want
+        NaiveDateTime::parse_from_str(s, DATE_FORMAT)
+            .map(|naive_date_time| ParsableDateTime(Local.from_utc_datetime(&naive_date_time)))
+    }
+}
+
+/// Formats the date the way Taskwarrior expects it, so that it can be used both in JSON and in
+/// command line modifications (e.g. `wait:20210501T120000Z`).
+impl fmt::Display for ParsableDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.with_timezone(&Utc).format(DATE_FORMAT))
+    }
+}
+
+impl<'de> Deserialize<'de> for ParsableDateTime {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ParsableDateTime, D::Error> {
+        deserializer.deserialize_str(DateTimeVisitor)
+    }
+}
+
+impl Serialize for ParsableDateTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct DateTimeVisitor;
+
+impl<'de> de::Visitor<'de> for DateTimeVisitor {
+    type Value = ParsableDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a string encoded in {}", DATE_FORMAT)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        s.parse()
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(s), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_full_task() -> serde_json::Result<()> {
+        let task: Task = serde_json::from_str(
+            r#"{
+                "id": 0,
+                "description": "write the report",
+                "end": "20210301T120000Z",
+                "entry": "20210201T091846Z",
+                "modified": "20210301T120000Z",
+                "project": "work.reports",
+                "priority": "H",
+                "status": "completed",
+                "uuid": "2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2",
+                "depends": "a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b,b7c8d9e0-1f2a-4b3c-8d4e-5f6a7b8c9d0e",
+                "annotations": [
+                    {"entry": "20210202T100000Z", "description": "see the draft"}
+                ],
+                "tags": ["taskn"],
+                "urgency": 4.5,
+                "estimate": 2,
+                "reviewed": "20210215T000000Z",
+                "effort": 3
+            }"#,
+        )?;
+
+        assert_eq!(task.id, None);
+        assert_eq!(task.project.as_deref(), Some("work.reports"));
+        assert_eq!(task.depends.as_ref().map(|depends| depends.len()), Some(2));
+        assert_eq!(
+            task.annotations.as_ref().unwrap()[0].description,
+            "see the draft"
+        );
+        assert_eq!(task.estimate, Some(2));
+        assert_eq!(
+            task.udas.get("reviewed"),
+            Some(&serde_json::Value::String("20210215T000000Z".to_string()))
+        );
+        assert_eq!(task.udas.get("effort"), Some(&serde_json::json!(3)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_round_trip() -> serde_json::Result<()> {
+        let json = serde_json::json!({
+            "description": "project:work is not an attribute here +nor is this a tag",
+            "uuid": "2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2",
+            "status": "pending",
+            "entry": "20210201T091846Z",
+            "due": "20210301T170000Z",
+            "tags": ["home"],
+            "depends": "a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b",
+            "annotations": [{"entry": "20210202T100000Z", "description": "a note"}],
+            "estimate": 4,
+            "effort": "M"
+        });
+
+        let mut exported = json.clone();
+        exported["id"] = serde_json::json!(7);
+        exported["urgency"] = serde_json::json!(3.2);
+        let task: Task = serde_json::from_value(exported)?;
+
+        assert_eq!(serde_json::to_value(&task)?, {
+            let mut json = json;
+            json["id"] = serde_json::json!(7);
+            json
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_time_round_trip() -> serde_json::Result<()> {
+        let date_time = ParsableDateTime::from(Utc.ymd(2021, 5, 1).and_hms(12, 30, 15));
+        assert_eq!(date_time.to_string(), "20210501T123015Z");
+
+        let json = serde_json::to_string(&date_time)?;
+        assert_eq!(json, r#""20210501T123015Z""#);
+        assert_eq!(serde_json::from_str::<ParsableDateTime>(&json)?, date_time);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_time_rejects_other_formats() {
+        assert!(serde_json::from_str::<ParsableDateTime>(r#""2021-05-01T12:30:15Z""#).is_err());
+        assert!("20210501".parse::<ParsableDateTime>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_depends_list() -> serde_json::Result<()> {
+        let task: Task = serde_json::from_str(
+            r#"{
+                "id": 3,
+                "description": "blocked",
+                "status": "pending",
+                "uuid": "2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2",
+                "depends": ["a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b"]
+            }"#,
+        )?;
+
+        assert_eq!(task.id, Some(3));
+        assert_eq!(
+            task.depends,
+            Some(vec!["a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b".to_string()])
+        );
+        Ok(())
+    }
+}
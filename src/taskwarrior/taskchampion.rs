@@ -0,0 +1,156 @@
+//! Reads tasks straight out of the SQLite database that Taskwarrior 3 keeps through TaskChampion.
+//! This skips spawning `task export` on every read, which adds up quickly in interactive mode.
+//! Writes still go through the `task` binary, so that Taskwarrior remains the only writer.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::attributes::task_from_attributes;
+use crate::taskwarrior::backend::{SubprocessBackend, TaskBackend};
+use crate::taskwarrior::filter::Filter;
+use crate::taskwarrior::Task;
+
+const DATABASE_NAME: &str = "taskchampion.sqlite3";
+
+pub struct TaskChampionBackend {
+    path: PathBuf,
+    connection: Connection,
+    fallback: SubprocessBackend,
+}
+
+impl TaskChampionBackend {
+    /// Opens the TaskChampion database inside of `data_location`, returning `None` if there isn't
+    /// one. `fallback` is used for writes, and for reads with filters that taskn can't evaluate.
+    pub fn open(data_location: &Path, fallback: SubprocessBackend) -> Result<Option<Self>> {
+        let path = data_location.join(DATABASE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let connection = Connection::open_with_flags(
+            &path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| store_error(&path, e))?;
+        Ok(Some(Self {
+            path,
+            connection,
+            fallback,
+        }))
+    }
+
+    /// Reads every task in the replica as its working set ID, UUID, and JSON-encoded attributes.
+    fn read_rows(&self) -> rusqlite::Result<Vec<(Option<usize>, String, String)>> {
+        let mut statement = self.connection.prepare(
+            "SELECT working_set.id, tasks.uuid, tasks.data FROM tasks
+             LEFT JOIN working_set ON working_set.uuid = tasks.uuid",
+        )?;
+        let rows = statement.query_map(NO_PARAMS, |row| {
+            Ok((
+                row.get::<_, Option<i64>>(0)?.map(|id| id as usize),
+                row.get(1)?,
+                row.get(2)?,
+            ))
+        })?;
+        rows.collect()
+    }
+}
+
+impl TaskBackend for TaskChampionBackend {
+    fn export(&self, filter: &[String]) -> Result<Vec<Task>> {
+        let parsed = match Filter::parse(filter) {
+            None => return self.fallback.export(filter),
+            Some(parsed) => parsed,
+        };
+
+        let rows = self.read_rows().map_err(|e| store_error(&self.path, e))?;
+        let mut tasks = Vec::new();
+        for (id, uuid, data) in rows.into_iter() {
+            let attributes: BTreeMap<String, String> =
+                serde_json::from_str(&data).map_err(|source| TasknError::Decode {
+                    context: format!("task {} in {}", uuid, self.path.display()),
+                    source,
+                })?;
+            let task = task_from_attributes(id, &uuid, &attributes)?;
+            if parsed.matches(&task) {
+                tasks.push(task);
+            }
+        }
+        // match the order of `task export`, which lists tasks by ID and then by entry
+        tasks.sort_by(|a, b| {
+            (a.id.is_none(), a.id, &a.entry)
+                .partial_cmp(&(b.id.is_none(), b.id, &b.entry))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(tasks)
+    }
+
+    fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()> {
+        self.fallback.modify(uuid, modifications)
+    }
+
+    fn import(&self, tasks: &[Task]) -> Result<()> {
+        self.fallback.import(tasks)
+    }
+
+    fn add(&self, description: &str, modifications: &[String]) -> Result<String> {
+        self.fallback.add(description, modifications)
+    }
+
+    fn config(&self, key: &str, value: &str) -> Result<()> {
+        self.fallback.config(key, value)
+    }
+
+    fn show(&self) -> Result<BTreeMap<String, String>> {
+        self.fallback.show()
+    }
+}
+
+fn store_error(path: &Path, e: rusqlite::Error) -> TasknError {
+    TasknError::Store {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_export_reads_replica() -> Result<()> {
+        let dir = TempDir::create("tc")?;
+        let data_location = dir.path();
+        {
+            let connection = Connection::open(data_location.join(DATABASE_NAME)).unwrap();
+            connection
+                .execute_batch(
+                    r#"
+                    CREATE TABLE tasks (uuid STRING PRIMARY KEY, data STRING);
+                    CREATE TABLE working_set (id INTEGER PRIMARY KEY, uuid STRING);
+                    INSERT INTO tasks VALUES (
+                        '2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2',
+                        '{"status":"pending","description":"pending task","entry":"1612171126","tag_home":""}'
+                    );
+                    INSERT INTO tasks VALUES (
+                        'a3b6f1d2-0b44-4e73-9d7b-1c2f3e4d5a6b',
+                        '{"status":"completed","description":"done task","entry":"1612171126","end":"1612200000"}'
+                    );
+                    INSERT INTO working_set VALUES (1, '2f3a4c7e-6c0f-4bd7-b1b4-0a4d2f26a5c2');
+                    "#,
+                )
+                .unwrap();
+        }
+
+        let backend = TaskChampionBackend::open(data_location, SubprocessBackend::new())?.unwrap();
+        let tasks = backend.export(&["status:pending".to_string(), "+home".to_string()])?;
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, Some(1));
+        assert_eq!(tasks[0].description, "pending task");
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+//! Reads and updates the user's Taskwarrior configuration. Rather than parsing taskrc files
+//! ourselves (which would mean handling `TASKRC`, XDG locations, `include`s, ...), we ask
+//! Taskwarrior for the effective configuration and write changes back through `task config`.
+use std::collections::BTreeMap;
+
+use crate::error::Result;
+use crate::taskwarrior::TaskBackend;
+
+pub struct Taskrc<'a> {
+    backend: &'a dyn TaskBackend,
+    values: BTreeMap<String, String>,
+}
+
+impl<'a> Taskrc<'a> {
+    /// Loads the effective configuration, as reported by `task _show`.
+    pub fn load(backend: &'a dyn TaskBackend) -> Result<Self> {
+        Ok(Self {
+            backend,
+            values: backend.show()?,
+        })
+    }
+
+    /// Returns the value of a configuration setting, if it's set to anything.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .get(key)
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Iterates over every configuration setting, in order of their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Sets `key` to `value` unless it's already set to that value. Returns whether the
+    /// configuration changed.
+    pub fn ensure(&mut self, key: &str, value: &str) -> Result<bool> {
+        if self.values.get(key).map(|current| current.as_str()) == Some(value) {
+            return Ok(false);
+        }
+
+        self.backend.config(key, value)?;
+        self.values.insert(key.to_string(), value.to_string());
+        Ok(true)
+    }
+
+    /// Defines a user defined attribute (UDA). `uda_type` is one of Taskwarrior's UDA types (e.g.
+    /// `string` or `numeric`). The label is only set if one is provided and the user hasn't
+    /// already picked their own.
+    pub fn define_uda(&mut self, name: &str, uda_type: &str, label: Option<&str>) -> Result<()> {
+        self.ensure(&format!("uda.{}.type", name), uda_type)?;
+        if let Some(label) = label {
+            let key = format!("uda.{}.label", name);
+            if self.get(&key).is_none() {
+                self.ensure(&key, label)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes `column` (e.g. `taskn_order+`) the first thing the report `name` sorts by, keeping
+    /// the rest of its sort order, so `report.next.sort=urgency-` becomes
+    /// `taskn_order+,urgency-`. Returns whether the configuration changed.
+    pub fn sort_report(&mut self, name: &str, column: &str) -> Result<bool> {
+        let key = format!("report.{}.sort", name);
+        let attribute = |column: &str| column.trim_end_matches(&['+', '-', '/'][..]).to_string();
+        let mut columns = vec![column.to_string()];
+        if let Some(sort) = self.get(&key) {
+            columns.extend(
+                sort.split(',')
+                    .filter(|other| attribute(other) != attribute(column))
+                    .map(|other| other.to_string()),
+            );
+        }
+        self.ensure(&key, &columns.join(","))
+    }
+}
+
+/// Parses the `key=value` lines printed by `task _show`.
+pub fn parse_show(output: &str) -> BTreeMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let equals = line.find('=')?;
+            Some((
+                line[..equals].trim().to_string(),
+                line[equals + 1..].trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+
+    #[test]
+    fn test_parse_show() {
+        let values = parse_show("data.location=~/.task\nreport.next.sort=urgency-\nverbose=\n");
+        assert_eq!(values["data.location"], "~/.task");
+        assert_eq!(values["report.next.sort"], "urgency-");
+        assert_eq!(values["verbose"], "");
+    }
+
+    #[test]
+    fn test_define_idempotently() -> Result<()> {
+        let backend = MemoryBackend::default();
+        backend.config("uda.estimate.label", "Est")?;
+
+        let mut taskrc = Taskrc::load(&backend)?;
+        taskrc.define_uda("estimate", "numeric", Some("Estimate"))?;
+        assert_eq!(
+            backend.config_value("uda.estimate.type").as_deref(),
+            Some("numeric")
+        );
+        assert_eq!(
+            backend.config_value("uda.estimate.label").as_deref(),
+            Some("Est")
+        );
+
+        let mut taskrc = Taskrc::load(&backend)?;
+        assert!(!taskrc.ensure("uda.estimate.type", "numeric")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_report() -> Result<()> {
+        let backend = MemoryBackend::default();
+        backend.config("report.next.sort", "urgency-,taskn_order-/")?;
+
+        let mut taskrc = Taskrc::load(&backend)?;
+        assert!(taskrc.sort_report("next", "taskn_order+")?);
+        assert!(!taskrc.sort_report("next", "taskn_order+")?);
+        assert!(taskrc.sort_report("list", "taskn_order+")?);
+        assert_eq!(
+            backend.config_value("report.next.sort").as_deref(),
+            Some("taskn_order+,urgency-")
+        );
+        assert_eq!(
+            backend.config_value("report.list.sort").as_deref(),
+            Some("taskn_order+")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+//! Writes changes to several tasks as one unit. Changes are queued on a [Transaction] and then
+//! imported in a single batch. If the import fails partway through, every task the transaction
+//! touched is put back the way it was, so an operation like a reorder is never left half-applied.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+
+use crate::error::{Result, TasknError};
+use crate::taskwarrior::{Difference, Task, TaskBackend};
+
+#[derive(Default)]
+pub struct Transaction {
+    tasks: Vec<Task>,
+}
+
+/// A change that a [Transaction] will make (or has made) to a single task. `before` is `None`
+/// when the transaction creates the task.
+pub struct PlannedChange {
+    pub before: Option<Task>,
+    pub after: Task,
+    pub differences: Vec<Difference>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `task` to be written, replacing any change already queued for the same task.
+    pub fn update(&mut self, task: Task) {
+        match self
+            .tasks
+            .iter_mut()
+            .find(|queued| queued.uuid == task.uuid)
+        {
+            Some(queued) => *queued = task,
+            None => self.tasks.push(task),
+        }
+    }
+
+    /// Compares the queued tasks against the tasks in `backend`. Tasks which wouldn't change are
+    /// left out.
+    pub fn plan(&self, backend: &dyn TaskBackend) -> Result<Vec<PlannedChange>> {
+        let mut current = Task::get_by_uuid(backend, &self.uuids())?;
+        let mut plan = Vec::new();
+        for task in self.tasks.iter() {
+            let before = current.remove(&task.uuid);
+            let differences = match &before {
+                Some(before) => before.diff(task)?,
+                None => Vec::new(),
+            };
+            if before.is_none() || !differences.is_empty() {
+                plan.push(PlannedChange {
+                    before,
+                    after: task.clone(),
+                    differences,
+                });
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Writes every queued change. If that fails, the tasks are restored to how they were before
+    /// the transaction, and the error says whether restoring them worked.
+    pub fn commit(self, backend: &dyn TaskBackend) -> Result<Vec<PlannedChange>> {
+        let plan = self.plan(backend)?;
+        if plan.is_empty() {
+            return Ok(plan);
+        }
+
+        let tasks = plan
+            .iter()
+            .map(|change| change.after.clone())
+            .collect::<Vec<Task>>();
+        if let Err(e) = backend.import(&tasks) {
+            return Err(TasknError::Transaction {
+                source: Box::new(e),
+                rollback: rollback(backend, &plan).err().map(Box::new),
+            });
+        }
+        Ok(plan)
+    }
+
+    fn uuids(&self) -> Vec<&str> {
+        self.tasks.iter().map(|task| task.uuid.as_str()).collect()
+    }
+}
+
+impl FromIterator<Task> for Transaction {
+    fn from_iter<I: IntoIterator<Item = Task>>(tasks: I) -> Self {
+        let mut transaction = Transaction::new();
+        for task in tasks {
+            transaction.update(task);
+        }
+        transaction
+    }
+}
+
+/// Puts every task in `plan` back the way it was. We don't know which of the tasks were written
+/// before the import failed, so this restores all of them; re-importing an unchanged task does
+/// nothing. Tasks which the transaction created are deleted.
+fn rollback(backend: &dyn TaskBackend, plan: &[PlannedChange]) -> Result<()> {
+    let created = plan
+        .iter()
+        .filter(|change| change.before.is_none())
+        .map(|change| change.after.uuid.as_str())
+        .collect::<Vec<&str>>();
+    let mut current: BTreeMap<String, Task> = Task::get_by_uuid(backend, &created)?;
+
+    let mut restored = Vec::new();
+    for change in plan.iter() {
+        match &change.before {
+            Some(before) => restored.push(before.clone()),
+            None => {
+                if let Some(mut task) = current.remove(&change.after.uuid) {
+                    task.status = "deleted".to_string();
+                    restored.push(task);
+                }
+            }
+        }
+    }
+    backend.import(&restored)
+}
+
+impl fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.after.id {
+            Some(id) => format!("task {}", id),
+            None => format!("task {}", self.after.uuid),
+        };
+        if self.before.is_none() {
+            return write!(f, "add {} '{}'", name, self.after.description);
+        }
+
+        write!(f, "modify {} '{}'", name, self.after.description)?;
+        for difference in self.differences.iter() {
+            write!(f, "\n  {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::taskwarrior::MemoryBackend;
+    use crate::test_support::task;
+
+    /// Applies only the first task of the first import, and then fails.
+    struct FlakyBackend {
+        inner: MemoryBackend,
+        imports: Cell<usize>,
+    }
+
+    impl TaskBackend for FlakyBackend {
+        fn export(&self, filter: &[String]) -> Result<Vec<Task>> {
+            self.inner.export(filter)
+        }
+
+        fn modify(&self, uuid: &str, modifications: &[String]) -> Result<()> {
+            self.inner.modify(uuid, modifications)
+        }
+
+        fn import(&self, tasks: &[Task]) -> Result<()> {
+            self.imports.set(self.imports.get() + 1);
+            if self.imports.get() > 1 {
+                return self.inner.import(tasks);
+            }
+            self.inner.import(&tasks[..1])?;
+            Err(TasknError::Invalid("disk full".to_string()))
+        }
+
+        fn add(&self, description: &str, modifications: &[String]) -> Result<String> {
+            self.inner.add(description, modifications)
+        }
+
+        fn config(&self, key: &str, value: &str) -> Result<()> {
+            self.inner.config(key, value)
+        }
+
+        fn show(&self) -> Result<BTreeMap<String, String>> {
+            self.inner.show()
+        }
+    }
+
+    #[test]
+    fn test_plan_skips_unchanged_tasks() -> Result<()> {
+        let backend = MemoryBackend::new(vec![task(1).with_estimate(0), task(2).with_estimate(1)]);
+        let transaction = vec![
+            task(1).with_estimate(0),
+            task(2).with_estimate(5),
+            task(3).with_estimate(2),
+        ]
+        .into_iter()
+        .collect::<Transaction>();
+        let plan = transaction.plan(&backend)?;
+        assert_eq!(plan.len(), 2);
+        assert_eq!(
+            plan[0].to_string(),
+            "modify task 2 'task 2'\n  estimate: 1 -> 5"
+        );
+        assert_eq!(plan[1].to_string(), "add task 3 'task 3'");
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_commit_rolls_back() -> Result<()> {
+        let backend = FlakyBackend {
+            inner: MemoryBackend::new(vec![task(1).with_estimate(0), task(2).with_estimate(1)]),
+            imports: Cell::new(0),
+        };
+        let transaction = vec![task(2).with_estimate(0), task(1).with_estimate(1)]
+            .into_iter()
+            .collect::<Transaction>();
+        match transaction.commit(&backend) {
+            Err(TasknError::Transaction { rollback, .. }) => assert!(rollback.is_none()),
+            _ => panic!("expected the commit to fail"),
+        }
+
+        let estimates = backend
+            .inner
+            .tasks()
+            .iter()
+            .map(|task| task.estimate.unwrap())
+            .collect::<Vec<i32>>();
+        assert_eq!(estimates, vec![0, 1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+//! Templates for new notes, kept in `<root_dir>/templates/`. When taskn opens a note which
+//! doesn't exist yet, it starts the note from the first of these templates which exists:
+//!
+//! 1. the template picked with `--template <name>`, i.e. `<name>.<file_format>`
+//! 2. `project.<project>.<file_format>`, falling back to parent projects (`project.work.acme`
+//!    and then `project.work`)
+//! 3. `tag.<tag>.<file_format>`, for each of the task's tags in turn
+//! 4. `default.<file_format>`
+//!
+//! Templates can refer to the task with `{{description}}`, `{{uuid}}`, `{{id}}`, `{{project}}`,
+//! `{{tags}}`, and `{{due}}`, and to the current date with `{{date}}`. Anything else in braces is
+//! left alone.
+//!
+//! A note which is still exactly what its template rendered to doesn't count as a note. Since
+//! the task (and the templates) may change after the note was started, the [fingerprint] of what
+//! was rendered is kept alongside the note rather than rendering the templates again.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::error::{Result, TasknError};
+use crate::front_matter;
+use crate::taskwarrior::Task;
+
+pub const TEMPLATES_DIR: &str = "templates";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Clone, Debug)]
+pub struct Templates {
+    dir: PathBuf,
+    file_format: String,
+}
+
+impl Templates {
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(dir: P, file_format: S) -> Self {
+        Self {
+            dir: dir.into(),
+            file_format: file_format.into(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path of the template called `name`, whether or not it exists.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, self.file_format))
+    }
+
+    /// Finds the template to start `task`'s note from, and renders it for `date`. Returns the
+    /// template's name along with what it rendered to. `name` is the template picked on the
+    /// command line, which must exist.
+    pub fn render_for(
+        &self,
+        task: &Task,
+        name: Option<&str>,
+        date: NaiveDate,
+    ) -> Result<Option<(String, String)>> {
+        if let Some(name) = name {
+            return match self.read(&self.path(name))? {
+                Some(template) => Ok(Some((name.to_string(), render(&template, task, date)))),
+                None => Err(TasknError::Invalid(format!(
+                    "no template named '{}' in {}",
+                    name,
+                    self.dir.display()
+                ))),
+            };
+        }
+
+        for name in candidates(task) {
+            if let Some(template) = self.read(&self.path(&name))? {
+                return Ok(Some((name, render(&template, task, date))));
+            }
+        }
+        Ok(None)
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<String>> {
+        match fs::read_to_string(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.error(path, e)),
+            Ok(template) => Ok(Some(template)),
+        }
+    }
+
+    fn error(&self, path: &Path, source: io::Error) -> TasknError {
+        TasknError::Note {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+/// The names of the templates which apply to `task`, from most to least specific.
+fn candidates(task: &Task) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(project) = &task.project {
+        let parts = project.split('.').collect::<Vec<&str>>();
+        for len in (1..=parts.len()).rev() {
+            names.push(format!("project.{}", parts[..len].join(".")));
+        }
+    }
+    for tag in task.tags.iter().flatten() {
+        names.push(format!("tag.{}", tag));
+    }
+    names.push("default".to_string());
+    names
+}
+
+/// Fingerprints the body of a note, ignoring front matter and leading and trailing whitespace.
+/// This is FNV-1a rather than [std::hash::Hash], whose output may change between Rust releases,
+/// since fingerprints are kept on disk.
+pub fn fingerprint(contents: &str) -> u64 {
+    front_matter::body(contents)
+        .trim()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// Fills in the variables in `template` for `task`. Variables the task has no value for are
+/// left empty.
+pub fn render(template: &str, task: &Task, date: NaiveDate) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            None => break,
+            Some(end) => start + end,
+        };
+        rendered.push_str(&rest[..start]);
+        match variable(rest[start + 2..end].trim(), task, date) {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn variable(name: &str, task: &Task, date: NaiveDate) -> Option<String> {
+    Some(match name {
+        "description" => task.description.clone(),
+        "uuid" => task.uuid.clone(),
+        "id" => task.id.map(|id| id.to_string()).unwrap_or_default(),
+        "project" => task.project.clone().unwrap_or_default(),
+        "tags" => task
+            .tags
+            .iter()
+            .flatten()
+            .filter(|tag| *tag != "taskn")
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(", "),
+        "due" => task
+            .due
+            .as_ref()
+            .map(|due| due.0.format(DATE_FORMAT).to_string())
+            .unwrap_or_default(),
+        "date" => date.format(DATE_FORMAT).to_string(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{task, TempDir};
+
+    #[test]
+    fn test_render_and_select() -> Result<()> {
+        let dir = TempDir::create("templates")?;
+        let templates = Templates::new(dir.path(), "md");
+        let write = |name: &str, contents: &str| {
+            fs::write(templates.path(name), contents).map_err(TasknError::Io)
+        };
+        write("default", "# {{description}}\n")?;
+        write("tag.bug", "# {{ description }} ({{tags}})\n")?;
+        write(
+            "project.work",
+            "# {{description}}\nproject: {{project}}, {{date}} {{other}}\n",
+        )?;
+
+        let task = Task {
+            project: Some("work.acme".to_string()),
+            ..task(3)
+                .with_description("fix it")
+                .with_tags(&["bug", "taskn"])
+        };
+        let date = NaiveDate::from_ymd(2021, 3, 1);
+        assert_eq!(
+            templates.render_for(&task, None, date)?.unwrap(),
+            (
+                "project.work".to_string(),
+                "# fix it\nproject: work.acme, 2021-03-01 {{other}}\n".to_string()
+            )
+        );
+        assert_eq!(
+            templates
+                .render_for(&task, Some("tag.bug"), date)?
+                .unwrap()
+                .1,
+            "# fix it (bug)\n"
+        );
+        assert!(templates.render_for(&task, Some("missing"), date).is_err());
+        let untagged = Task {
+            project: None,
+            tags: None,
+            ..task.clone()
+        };
+        assert_eq!(
+            templates.render_for(&untagged, None, date)?.unwrap().0,
+            "default"
+        );
+
+        assert_eq!(
+            fingerprint("# fix it\n"),
+            fingerprint("+++\ndescription = \"fix it\"\n+++\n\n# fix it")
+        );
+        assert_ne!(fingerprint("# fix it"), fingerprint("# fix it\nsome notes"));
+        Ok(())
+    }
+}
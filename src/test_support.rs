@@ -0,0 +1,117 @@
+//! Fixtures shared by the unit tests.
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::Result;
+use crate::opt::Opt;
+use crate::taskwarrior::{ParsableDateTime, Task};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of the test's own in the system's temporary directory, which is removed again
+/// (along with everything in it) when this is dropped, whether or not the test passed.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Picks a directory named after `name` that no other test uses, without creating it yet.
+    pub fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!(
+            "taskn-{}-{}-{}",
+            name,
+            process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        TempDir { path }
+    }
+
+    /// Like [TempDir::new], but creates the directory too.
+    pub fn create(name: &str) -> Result<Self> {
+        let dir = TempDir::new(name);
+        fs::create_dir_all(&dir.path)?;
+        Ok(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // the directory may never have been created, and a leftover one is harmless anyway
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A pending task with the ID `id`, a UUID derived from it, and a description of `task <id>`.
+/// The `with_*` methods fill in the attributes a test cares about.
+pub fn task(id: usize) -> Task {
+    Task {
+        id: Some(id),
+        description: format!("task {}", id),
+        uuid: format!("00000000-0000-4000-8000-{:012x}", id),
+        status: "pending".to_string(),
+        ..Task::default()
+    }
+}
+
+impl Task {
+    pub fn with_description(self, description: &str) -> Task {
+        Task {
+            description: description.to_string(),
+            ..self
+        }
+    }
+
+    pub fn with_status(self, status: &str) -> Task {
+        Task {
+            status: status.to_string(),
+            ..self
+        }
+    }
+
+    pub fn with_tags(self, tags: &[&str]) -> Task {
+        Task {
+            tags: Some(tags.iter().map(|tag| tag.to_string()).collect()),
+            ..self
+        }
+    }
+
+    /// Sets `modified` from a timestamp in Taskwarrior's format, e.g. `20210101T000000Z`.
+    pub fn with_modified(self, modified: &str) -> Task {
+        Task {
+            modified: Some(modified.parse::<ParsableDateTime>().unwrap()),
+            ..self
+        }
+    }
+
+    pub fn with_estimate(self, estimate: i32) -> Task {
+        Task {
+            estimate: Some(estimate),
+            ..self
+        }
+    }
+
+    pub fn with_order(self, taskn_order: f64) -> Task {
+        Task {
+            taskn_order: Some(taskn_order),
+            ..self
+        }
+    }
+}
+
+/// Options for running a command against a root directory of the test's own, with an editor
+/// which leaves notes as they are.
+pub fn opt(root_dir: &Path) -> Opt {
+    Opt {
+        editor: "true".to_string(),
+        file_format: "md".to_string(),
+        root_dir: root_dir.to_string_lossy().to_string(),
+        ..Opt::default()
+    }
+}